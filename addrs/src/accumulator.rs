@@ -0,0 +1,170 @@
+use std::{collections::HashSet, ops::AddAssign};
+
+use crate::{scalar::Scalar, Expr};
+
+/// When an [`Accumulator`] compresses its sum.
+///
+/// Both thresholds are measured from the last compression.
+/// The default policy never compresses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressPolicy {
+    max_generation: Option<usize>,
+    max_nodes: Option<usize>,
+}
+
+impl CompressPolicy {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Compresses when the generation grows more than `n`.
+    #[inline]
+    pub fn max_generation(mut self, n: usize) -> Self {
+        self.max_generation = Some(n);
+        self
+    }
+    /// Compresses when more than `n` nodes are added.
+    ///
+    /// Nodes are counted incrementally, so that each `add` only visits nodes new to the sum.
+    #[inline]
+    pub fn max_nodes(mut self, n: usize) -> Self {
+        self.max_nodes = Some(n);
+        self
+    }
+}
+
+/// Sum of many expressions compressed automatically by a [`CompressPolicy`].
+///
+/// This is a replacement of `res += term` in a loop followed by `res.compress()`,
+/// which keeps the whole graph of the loop until the end.
+#[derive(Debug, Clone)]
+pub struct Accumulator<T> {
+    sum: Expr<T>,
+    policy: CompressPolicy,
+    base: usize,
+    nodes: usize,
+    // addresses of nodes counted since the last compression, all of which are kept by `sum`
+    visited: HashSet<usize>,
+}
+
+impl<T: Scalar> Accumulator<T> {
+    #[inline]
+    pub fn new(policy: CompressPolicy) -> Self {
+        Self {
+            sum: Expr::constant(T::zero()),
+            policy,
+            base: 0,
+            nodes: 0,
+            visited: HashSet::new(),
+        }
+    }
+    #[inline]
+    pub fn policy(&self) -> &CompressPolicy {
+        &self.policy
+    }
+    #[inline]
+    pub fn sum(&self) -> &Expr<T> {
+        &self.sum
+    }
+    #[inline]
+    pub fn into_expr(self) -> Expr<T> {
+        self.sum
+    }
+
+    pub fn add(&mut self, term: Expr<T>) {
+        if self.policy.max_nodes.is_some() {
+            self.nodes += term._ref().count_new_nodes(&mut self.visited) + 1;
+        }
+        let sum = std::mem::replace(&mut self.sum, Expr::constant(T::zero()));
+        self.sum = sum + term;
+
+        let deep = match self.policy.max_generation {
+            Some(n) => self.sum.generation() - self.base > n,
+            None => false,
+        };
+        let large = matches!(self.policy.max_nodes, Some(n) if self.nodes > n);
+        if deep || large {
+            self.compress();
+        }
+    }
+
+    /// Compresses the sum regardless of the policy.
+    pub fn compress(&mut self) {
+        self.sum.compress();
+        self.base = self.sum.generation();
+        self.nodes = 0;
+        self.visited.clear();
+    }
+}
+
+impl<T: Scalar> AddAssign<Expr<T>> for Accumulator<T> {
+    #[inline]
+    fn add_assign(&mut self, term: Expr<T>) {
+        self.add(term);
+    }
+}
+
+impl<T: Scalar> Extend<Expr<T>> for Accumulator<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Expr<T>>>(&mut self, terms: I) {
+        terms.into_iter().for_each(|t| self.add(t));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scalar::Exp, Graph, VarGroup};
+
+    use super::*;
+
+    #[test]
+    fn test_accumulator() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.5_f64).into_expr();
+        let y = grp.val(1.5_f64).into_expr();
+        let term = |k: usize| (x.clone() * k as f64 * 1e-3).exp() * &y - &x;
+
+        let mut expected = Expr::constant(0.0);
+        for k in 0..1000 {
+            expected += term(k);
+        }
+        let policies = [
+            CompressPolicy::new().max_generation(10),
+            CompressPolicy::new().max_nodes(100),
+        ];
+        for policy in policies {
+            let mut acc = Accumulator::new(policy);
+            acc.extend((0..1000).map(term));
+            assert!(Graph::from(acc.sum()).nodes().len() < 100);
+            assert!((acc.sum().output() - expected.output()).abs() < 1e-9);
+            let (actual, expected) = (acc.sum().grads(), expected.grads());
+            for (id, g) in &expected {
+                assert!((actual[id] - g).abs() < 1e-9);
+            }
+        }
+
+        let mut acc = Accumulator::new(CompressPolicy::new());
+        acc += term(0);
+        acc += term(1);
+        // never compressed
+        assert_eq!(acc.sum().generation(), 7);
+    }
+
+    #[test]
+    fn test_accumulator_shared() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.5_f64).into_expr();
+        // subgraph of 200 nodes shared by all terms
+        let mut shared = x.clone();
+        for _ in 0..100 {
+            shared = shared * 0.5 + &x;
+        }
+        let mut acc = Accumulator::new(CompressPolicy::new().max_nodes(500));
+        acc.extend((0..100).map(|k| shared.clone() * k as f64));
+        // shared nodes are counted once and the sum is never compressed
+        assert!(Graph::from(acc.sum()).nodes().len() > 300);
+        // new nodes are still counted
+        acc.extend((0..50).map(|k| (x.clone() * k as f64).exp()));
+        assert!(Graph::from(acc.sum()).nodes().len() < 150);
+    }
+}
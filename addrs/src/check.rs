@@ -0,0 +1,220 @@
+use crate::{Expr, Id, VarGroup};
+
+/// Scheme of finite differences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FiniteDiff {
+    /// `(f(x + h) - f(x - h)) / 2h`
+    #[default]
+    Central,
+    /// `(f(x + h) - f(x)) / h`
+    Forward,
+}
+
+/// Options of [`check_gradients_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct CheckOptions {
+    scheme: FiniteDiff,
+    step: f64,
+}
+
+impl Default for CheckOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            scheme: FiniteDiff::Central,
+            step: 1e-6,
+        }
+    }
+}
+
+impl CheckOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    #[inline]
+    pub fn scheme(mut self, scheme: FiniteDiff) -> Self {
+        self.scheme = scheme;
+        self
+    }
+    /// Relative bump size. Each input `x` is bumped by `step * max(1, |x|)`.
+    #[inline]
+    pub fn step(mut self, step: f64) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+/// Comparison of a derivative with its finite-difference approximation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientError {
+    /// Derivative calculated by [`Expr::grads`].
+    pub analytic: f64,
+    /// Derivative approximated by finite differences.
+    pub numeric: f64,
+    pub abs: f64,
+    /// Absolute error divided by the larger magnitude of the two derivatives.
+    /// This is zero if both are zero.
+    pub rel: f64,
+}
+
+/// Result of [`check_gradients`] for each input.
+#[derive(Debug, Clone)]
+pub struct GradientReport {
+    entries: Vec<(Id, GradientError)>,
+}
+
+impl GradientReport {
+    /// Variables and errors in the order of inputs.
+    #[inline]
+    pub fn entries(&self) -> &[(Id, GradientError)] {
+        &self.entries
+    }
+    #[inline]
+    pub fn get(&self, id: &Id) -> Option<&GradientError> {
+        self.entries.iter().find(|(i, _)| i == id).map(|(_, e)| e)
+    }
+    /// NaN if any error is NaN, so that undefined derivatives never pass a tolerance.
+    #[inline]
+    pub fn max_abs(&self) -> f64 {
+        self.entries.iter().map(|(_, e)| e.abs).fold(0.0, _max)
+    }
+    /// NaN if any error is NaN, so that undefined derivatives never pass a tolerance.
+    #[inline]
+    pub fn max_rel(&self) -> f64 {
+        self.entries.iter().map(|(_, e)| e.rel).fold(0.0, _max)
+    }
+}
+
+/// Maximum propagating NaN unlike [`f64::max`].
+#[inline]
+fn _max(a: f64, b: f64) -> f64 {
+    match a.is_nan() || b.is_nan() {
+        true => f64::NAN,
+        false => a.max(b),
+    }
+}
+
+/// Compares derivatives of `f` at `vals` with central finite differences.
+///
+/// `f` receives variables with values of `vals`, which are named `check#k` for `k`-th input.
+pub fn check_gradients<F>(vals: &[f64], f: F) -> GradientReport
+where
+    F: Fn(&[Expr<f64>]) -> Expr<f64>,
+{
+    check_gradients_with(vals, f, &CheckOptions::default())
+}
+
+/// Compares derivatives of `f` at `vals` with finite differences configured by `opts`.
+pub fn check_gradients_with<F>(vals: &[f64], f: F, opts: &CheckOptions) -> GradientReport
+where
+    F: Fn(&[Expr<f64>]) -> Expr<f64>,
+{
+    let grp = VarGroup::new("check");
+    let vars: Vec<_> = vals.iter().map(|v| grp.val(*v).into_expr()).collect();
+    let res = f(&vars);
+    let grads = res.grads();
+
+    let eval = |k: usize, h: f64| {
+        let mut xs: Vec<_> = vals.to_vec();
+        xs[k] += h;
+        let xs: Vec<_> = xs.into_iter().map(Expr::constant).collect();
+        *f(&xs).output()
+    };
+    let entries = vars
+        .iter()
+        .enumerate()
+        .map(|(k, x)| {
+            let id = x.as_var().unwrap().id().clone();
+            let h = opts.step * vals[k].abs().max(1.0);
+            let numeric = match opts.scheme {
+                FiniteDiff::Central => (eval(k, h) - eval(k, -h)) / (2.0 * h),
+                FiniteDiff::Forward => (eval(k, h) - res.output()) / h,
+            };
+            let analytic = grads.get(&id).copied().unwrap_or(0.0);
+            let abs = (analytic - numeric).abs();
+            let scale = analytic.abs().max(numeric.abs());
+            let rel = if scale == 0.0 { 0.0 } else { abs / scale };
+            let err = GradientError {
+                analytic,
+                numeric,
+                abs,
+                rel,
+            };
+            (id, err)
+        })
+        .collect();
+    GradientReport { entries }
+}
+
+/// Asserts that derivatives of `f` agree with finite differences
+/// and with forward mode derivatives of `fd`, which is usually the same generic function as `f`.
+#[cfg(test)]
+pub(crate) fn assert_gradients<F, D>(vals: &[f64], f: F, fd: D)
+where
+    F: Fn(&[Expr<f64>]) -> Expr<f64>,
+    D: Fn(&[crate::scalar::Dual<f64>]) -> crate::scalar::Dual<f64>,
+{
+    use crate::scalar::Dual;
+
+    let report = check_gradients(vals, &f);
+    assert!(report.max_rel() < 1e-6, "{:?}", report);
+
+    let grp = VarGroup::new("x");
+    let xs: Vec<_> = vals.iter().map(|v| grp.val(*v).into_expr()).collect();
+    let grads = f(&xs).grads();
+    for (k, x) in xs.iter().enumerate() {
+        let duals: Vec<_> = vals
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Dual::new(*v, if i == k { 1.0 } else { 0.0 }))
+            .collect();
+        assert!((Dual::tan(&fd(&duals)) - grads[x]).abs() < 1e-12, "{}", k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scalar::{Exp, Log, Sqrt};
+
+    use super::*;
+
+    #[test]
+    fn test_check_gradients() {
+        let f = |x: &[Expr<f64>]| {
+            let d = ((x[0].clone() / &x[1]).log() + x[2].clone() * &x[2] * 0.5) / &x[2];
+            x[0].clone() * d.clone().exp() - (x[1].clone() * &x[3]).sqrt() * d
+        };
+        let vals = [100.0, 95.0, 0.2, 0.5];
+        let report = check_gradients(&vals, f);
+        assert_eq!(report.entries().len(), 4);
+        assert!(report.max_rel() < 1e-6, "{:?}", report);
+
+        let forward =
+            check_gradients_with(&vals, f, &CheckOptions::new().scheme(FiniteDiff::Forward));
+        assert!(forward.max_rel() > report.max_rel());
+        assert!(forward.max_rel() < 1e-3, "{:?}", forward);
+    }
+
+    #[test]
+    fn test_check_gradients_wrong() {
+        // derivative of `x * x` is wrongly recorded as zero by detaching one operand
+        let report = check_gradients(&[3.0], |x| x[0].clone() * Expr::constant(*x[0].output()));
+        let (_, err) = &report.entries()[0];
+        assert_eq!(err.analytic, 3.0);
+        assert!((err.numeric - 6.0).abs() < 1e-6);
+        assert!((err.rel - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_check_gradients_nan() {
+        // `sqrt` at zero has an infinite derivative and a NaN finite difference
+        let report = check_gradients(&[0.0, 1.0], |x| x[0].clone().sqrt() + &x[1]);
+        assert!(report.entries()[0].1.rel.is_nan());
+        assert!(report.max_abs().is_nan());
+        assert!(report.max_rel().is_nan());
+
+        let report = check_gradients(&[1.0], |x| x[0].clone() * f64::NAN);
+        assert!(report.max_rel().is_nan());
+    }
+}
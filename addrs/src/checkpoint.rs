@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    private::{_Adjoint, _Expr, _Rc, _Row, _Sink},
+    scalar::Scalar,
+    Expr, Id, Jacobian, VarGroup,
+};
+
+/// Checkpointing for long time-stepping loops.
+///
+/// Instead of keeping the whole graph of a loop,
+/// only values of the state at segment boundaries are stored during the forward pass.
+/// Graphs of segments are recomputed from the stored values one by one during the backward pass.
+/// Hence, memory usage is bounded by `budget` checkpoints plus the graph of one segment
+/// at the cost of evaluating each step twice.
+/// A larger budget stores more state values and makes segments, i.e. graphs alive at once, shorter.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint {
+    budget: usize,
+}
+
+impl Checkpoint {
+    /// `budget` is the maximum number of checkpoints to be stored.
+    ///
+    /// # Panics
+    /// Panics if `budget` is zero.
+    #[inline]
+    pub fn new(budget: usize) -> Self {
+        assert!(budget > 0, "budget must be positive");
+        Self { budget }
+    }
+    #[inline]
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Runs `steps` steps of `step` from `init` and returns the final state.
+    ///
+    /// `step` receives the step index and the current state, and returns the next state,
+    /// whose length must be the same as that of `init`.
+    /// It may capture other expressions, for example model parameters.
+    ///
+    /// Each element of the result is a compressed expression,
+    /// so that it can be used with any other operations and `grads` as usual.
+    pub fn run<T, F>(&self, init: &[Expr<T>], steps: usize, mut step: F) -> Vec<Expr<T>>
+    where
+        T: Scalar,
+        F: FnMut(usize, &[Expr<T>]) -> Vec<Expr<T>>,
+    {
+        let dim = init.len();
+        let stride = steps.div_ceil(self.budget);
+
+        // forward pass keeping only values at checkpoints
+        let mut checkpoints = Vec::new();
+        let mut vals: Vec<T> = init.iter().map(|e| e.output().clone()).collect();
+        for k in 0..steps {
+            if k % stride == 0 {
+                checkpoints.push((k, vals.clone()));
+            }
+            let state: Vec<_> = vals.drain(..).map(Expr::constant).collect();
+            vals = step(k, &state).iter().map(|e| e.output().clone()).collect();
+            assert_eq!(vals.len(), dim, "dimension of the state must not change");
+        }
+
+        // backward pass recomputing segments
+        // adjs[i] holds derivatives of outputs by i-th state at the current checkpoint,
+        // keyed by indices of outputs which depend on the state.
+        let mut adjs: Vec<_> = (0..dim).map(|i| _Row(vec![(i, T::one())])).collect();
+        let mut grads = BTreeMap::new();
+        let mut gen = 0;
+        for (k, (begin, vals)) in checkpoints.iter().enumerate().rev() {
+            let end = checkpoints.get(k + 1).map_or(steps, |(end, _)| *end);
+            let grp = VarGroup::new("checkpoint");
+            let mut state: Vec<_> = vals
+                .iter()
+                .map(|v| grp.val(v.clone()).into_expr())
+                .collect();
+            for k in *begin..end {
+                state = step(k, &state);
+            }
+            gen += state.iter().map(Expr::generation).max().unwrap_or(0);
+            adjs = _pullback(&Expr::jacobian(&state), &adjs, &mut grads, Some(&grp));
+        }
+        _pullback(&Expr::jacobian(init), &adjs, &mut grads, None);
+        gen += init.iter().map(Expr::generation).max().unwrap_or(0);
+
+        let mut gss = vec![BTreeMap::new(); dim];
+        for (id, row) in grads {
+            for (o, g) in row.0 {
+                gss[o].insert(id.clone(), g);
+            }
+        }
+
+        vals.into_iter()
+            .zip(gss)
+            .map(|(o, gs)| {
+                let gs = _Rc::new(gs);
+                _Expr::Compressed { g: gen, o, gs }.into()
+            })
+            .collect()
+    }
+}
+
+/// Multiplies adjoints of outputs to a jacobian of a segment.
+///
+/// Derivatives by states of `grp` are returned
+/// and those by other variables are accumulated into `grads`.
+/// Both are sparse, so the cost is proportional to non-zero entries rather than `dim²`.
+fn _pullback<T: Scalar>(
+    jac: &Jacobian<T>,
+    adjs: &[_Row<T>],
+    grads: &mut BTreeMap<Id, _Row<T>>,
+    grp: Option<&VarGroup>,
+) -> Vec<_Row<T>> {
+    let mut res = vec![_Row(vec![]); adjs.len()];
+    for (i, j, x) in jac.triplets() {
+        if adjs[i].0.is_empty() {
+            continue;
+        }
+        let id = &jac.ids()[j];
+        let g = adjs[i].clone().scaled(x);
+        match grp {
+            Some(grp) if grp.id() == id.group => res[id.num].merge(g),
+            _ => grads.add(id, g),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scalar::{Exp, Sqrt};
+
+    use super::*;
+
+    #[test]
+    fn test_checkpoint() {
+        let grp = VarGroup::new("x");
+        let mu = grp.val(0.05_f64).into_expr();
+        let sigma = grp.val(0.2_f64).into_expr();
+        let init = vec![grp.val(100.0).into_expr(), grp.val(1.0).into_expr()];
+        let dt = 0.01;
+        let step = |_: usize, x: &[Expr<f64>]| {
+            let s = x[0].clone() * (mu.clone() * dt).exp() + sigma.clone() * x[0].clone().sqrt();
+            let df = x[1].clone() * (-(s.clone() * 1e-4) * dt).exp();
+            vec![s, df]
+        };
+
+        let mut expected = init.clone();
+        for k in 0..100 {
+            expected = step(k, &expected);
+        }
+        for budget in [1, 3, 100, 1000] {
+            let actual = Checkpoint::new(budget).run(&init, 100, step);
+            for (actual, expected) in actual.iter().zip(&expected) {
+                assert!((actual.output() - expected.output()).abs() < 1e-10);
+                let (actual, expected) = (actual.grads(), expected.grads());
+                assert_eq!(actual.len(), expected.len());
+                for (id, g) in expected {
+                    assert!((actual[&id] - g).abs() < 1e-10, "{:?}", (budget, id));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt::Debug;
+
+use crate::private::{_Rc, MaybeSync};
+
+/// User-defined unary operation recorded as a single node.
+///
+/// Operations are shared between expressions and graphs,
+/// so they must be thread-safe with the `sync` feature.
+pub trait UnaryOp<T>: Debug + MaybeSync {
+    /// Name used in formulas and graph renderings.
+    fn name(&self) -> &str;
+    fn eval(&self, x: &T) -> T;
+    /// Derivative at `x`, where `o` is the output at `x`.
+    fn partial(&self, x: &T, o: &T) -> T;
+}
+
+/// User-defined binary operation recorded as a single node.
+///
+/// Operations are shared between expressions and graphs,
+/// so they must be thread-safe with the `sync` feature.
+pub trait BinaryOp<T>: Debug + MaybeSync {
+    /// Name used in formulas and graph renderings.
+    fn name(&self) -> &str;
+    fn eval(&self, l: &T, r: &T) -> T;
+    /// Derivatives by `l` and `r`, where `o` is the output at `(l, r)`.
+    fn partials(&self, l: &T, r: &T, o: &T) -> (T, T);
+}
+
+/// Shared handle of a [`UnaryOp`].
+///
+/// Handles are equal only if they share the same operation.
+pub struct CustomUnary<T>(_Rc<dyn UnaryOp<T>>);
+
+impl<T> CustomUnary<T> {
+    #[inline]
+    pub fn new(op: impl UnaryOp<T> + 'static) -> Self {
+        Self(_Rc::new(op))
+    }
+    #[inline]
+    pub fn op(&self) -> &dyn UnaryOp<T> {
+        self.0.as_ref()
+    }
+    /// Returns the output and the partial derivative at `x`.
+    #[inline]
+    pub fn eval(&self, x: &T) -> (T, T) {
+        let o = self.0.eval(x);
+        let g = self.0.partial(x, &o);
+        (o, g)
+    }
+}
+
+impl<T> Clone for CustomUnary<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<T> Debug for CustomUnary<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl<T> PartialEq for CustomUnary<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        _Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Shared handle of a [`BinaryOp`].
+///
+/// Handles are equal only if they share the same operation.
+pub struct CustomBinary<T>(_Rc<dyn BinaryOp<T>>);
+
+impl<T> CustomBinary<T> {
+    #[inline]
+    pub fn new(op: impl BinaryOp<T> + 'static) -> Self {
+        Self(_Rc::new(op))
+    }
+    #[inline]
+    pub fn op(&self) -> &dyn BinaryOp<T> {
+        self.0.as_ref()
+    }
+    /// Returns the output and the partial derivatives at `(l, r)`.
+    #[inline]
+    pub fn eval(&self, l: &T, r: &T) -> (T, T, T) {
+        let o = self.0.eval(l, r);
+        let (gl, gr) = self.0.partials(l, r, &o);
+        (o, gl, gr)
+    }
+}
+
+impl<T> Clone for CustomBinary<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<T> Debug for CustomBinary<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl<T> PartialEq for CustomBinary<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        _Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check_gradients, graph::Node, Expr, Graph, Replay, Tape, VarGroup};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Logistic;
+
+    impl UnaryOp<f64> for Logistic {
+        fn name(&self) -> &str {
+            "logistic"
+        }
+        fn eval(&self, x: &f64) -> f64 {
+            1.0 / (1.0 + (-x).exp())
+        }
+        fn partial(&self, _: &f64, o: &f64) -> f64 {
+            o * (1.0 - o)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Hypot;
+
+    impl BinaryOp<f64> for Hypot {
+        fn name(&self) -> &str {
+            "hypot"
+        }
+        fn eval(&self, l: &f64, r: &f64) -> f64 {
+            l.hypot(*r)
+        }
+        fn partials(&self, l: &f64, r: &f64, o: &f64) -> (f64, f64) {
+            (l / o, r / o)
+        }
+    }
+
+    #[test]
+    fn test_custom() {
+        let logistic = CustomUnary::new(Logistic);
+        let hypot = CustomBinary::new(Hypot);
+        let f = |xs: &[Expr<f64>]| {
+            let h = hypot.apply(xs[0].clone(), xs[1].clone());
+            logistic.apply(h * 0.5)
+        };
+        let report = check_gradients(&[3.0, -4.0], f);
+        assert!(report.max_rel() < 1e-6, "{:?}", report);
+
+        let grp = VarGroup::new("x");
+        let xs = [grp.val(3.0).into_expr(), grp.val(-4.0).into_expr()];
+        let e = f(&xs);
+        let o = 1.0 / (1.0 + (-2.5_f64).exp());
+        assert_eq!(*e.output(), o);
+        assert_eq!(e.to_string(), "logistic(hypot(x#0, x#1) * 0.5)");
+
+        // each operation is a single node
+        let graph = Graph::from(&e);
+        assert_eq!(graph.nodes().len(), 6);
+        assert!(matches!(
+            &graph.nodes()[graph.outputs()[0]],
+            Node::CustomUnary { op, .. } if *op == logistic
+        ));
+
+        // re-evaluated at new values
+        let id = xs[1].as_var().unwrap().id();
+        let mut replay = Replay::new(std::slice::from_ref(&e));
+        assert!(replay.set(id, 4.0));
+        replay.forward();
+        assert_eq!(*replay.output(0), o);
+        let g = o * (1.0 - o) * 0.5 * 0.8;
+        assert!((replay.grads(0)[&xs[1]] - g).abs() < 1e-15);
+
+        let mut program = e.compile();
+        let mut grad = [0.0; 2];
+        assert_eq!(program.eval_grad(&[3.0, 4.0], &mut grad), o);
+        assert!((grad[1] - g).abs() < 1e-15);
+
+        // restored and taped expressions agree
+        let restored = graph.to_exprs().unwrap().pop().unwrap();
+        assert_eq!(restored.grads()[&xs[0]], e.grads()[&xs[0]]);
+        let tape = Tape::new();
+        let ts: Vec<_> = xs
+            .iter()
+            .map(|x| tape.var(x.as_var().unwrap().clone()))
+            .collect();
+        let t = f(&ts);
+        assert_eq!(*t.output(), o);
+        assert_eq!(t.grads()[&xs[0]], e.grads()[&xs[0]]);
+    }
+
+    /// Operation caching outputs, which is not thread-safe.
+    #[cfg(not(feature = "sync"))]
+    #[derive(Debug, Default)]
+    struct Cached(std::rc::Rc<std::cell::RefCell<Vec<f64>>>);
+
+    #[cfg(not(feature = "sync"))]
+    impl UnaryOp<f64> for Cached {
+        fn name(&self) -> &str {
+            "cached"
+        }
+        fn eval(&self, x: &f64) -> f64 {
+            let o = x.exp();
+            self.0.borrow_mut().push(o);
+            o
+        }
+        fn partial(&self, _: &f64, o: &f64) -> f64 {
+            *o
+        }
+    }
+
+    #[cfg(not(feature = "sync"))]
+    #[test]
+    fn test_custom_single_threaded() {
+        let cache = std::rc::Rc::default();
+        let op = CustomUnary::new(Cached(std::rc::Rc::clone(&cache)));
+        let x = VarGroup::new("x").val(0.0_f64).into_expr();
+        let e = op.apply(x.clone());
+        assert_eq!((*e.output(), e.grads()[&x]), (1.0, 1.0));
+        assert_eq!(*cache.borrow(), [1.0]);
+    }
+}
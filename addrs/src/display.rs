@@ -0,0 +1,417 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    graph::{BinaryKind, CmpKind, Node, UnaryKind},
+    Expr, Graph, Id,
+};
+
+/// Precedence of a rendered subexpression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum _Prec {
+    Add,
+    Mul,
+    Neg,
+    Pow,
+    Atom,
+}
+
+type _Term = (String, _Prec);
+
+/// Renders formulas as plain text or LaTeX.
+struct _Fmt {
+    latex: bool,
+}
+
+impl _Fmt {
+    #[inline]
+    fn paren(&self, (s, _): _Term, required: bool) -> String {
+        match (required, self.latex) {
+            (false, _) => s,
+            (true, false) => format!("({})", s),
+            (true, true) => format!("\\left({}\\right)", s),
+        }
+    }
+
+    fn var(&self, id: &Id) -> _Term {
+        let s = match self.latex {
+            false => format!("{}#{}", id.name, id.num),
+            true => format!("\\mathrm{{{}}}_{{{}}}", id.name, id.num),
+        };
+        (s, _Prec::Atom)
+    }
+
+    fn constant<T: Display>(&self, c: &T) -> _Term {
+        let s = c.to_string();
+        match s.starts_with('-') {
+            true => (s, _Prec::Neg),
+            false => (s, _Prec::Atom),
+        }
+    }
+
+    fn compressed(&self, ids: Vec<_Term>) -> _Term {
+        let ids: Vec<_> = ids.into_iter().map(|(s, _)| s).collect();
+        let s = match self.latex {
+            false => format!("compressed({})", ids.join(", ")),
+            true => format!("\\mathrm{{compressed}}\\left({}\\right)", ids.join(", ")),
+        };
+        (s, _Prec::Atom)
+    }
+
+    /// `latex` is the name of a LaTeX command.
+    fn func(&self, name: &str, latex: &str, x: _Term) -> _Term {
+        let s = match self.latex {
+            false => format!("{}({})", name, x.0),
+            true => format!("\\{}\\left({}\\right)", latex, x.0),
+        };
+        (s, _Prec::Atom)
+    }
+    fn func2(&self, name: &str, latex: &str, l: _Term, r: _Term) -> _Term {
+        let s = match self.latex {
+            false => format!("{}({}, {})", name, l.0, r.0),
+            true => format!("\\{}\\left({}, {}\\right)", latex, l.0, r.0),
+        };
+        (s, _Prec::Atom)
+    }
+
+    fn unary(&self, op: UnaryKind, x: _Term) -> _Term {
+        match op {
+            UnaryKind::Neg => {
+                let p = x.1 <= _Prec::Neg;
+                (format!("-{}", self.paren(x, p)), _Prec::Neg)
+            }
+            UnaryKind::Sqrt if self.latex => (format!("\\sqrt{{{}}}", x.0), _Prec::Atom),
+            UnaryKind::Sqrt => self.func("sqrt", "sqrt", x),
+            UnaryKind::Exp => self.func("exp", "exp", x),
+            UnaryKind::Log => self.func("log", "ln", x),
+            UnaryKind::Powi(n) => self.pow(x, self.constant(&n)),
+            UnaryKind::Sin => self.func("sin", "sin", x),
+            UnaryKind::Cos => self.func("cos", "cos", x),
+            UnaryKind::Tan => self.func("tan", "tan", x),
+            UnaryKind::Asin => self.func("asin", "arcsin", x),
+            UnaryKind::Acos => self.func("acos", "arccos", x),
+            UnaryKind::Atan => self.func("atan", "arctan", x),
+            UnaryKind::Tanh => self.func("tanh", "tanh", x),
+            UnaryKind::Sinh => self.func("sinh", "sinh", x),
+            UnaryKind::Cosh => self.func("cosh", "cosh", x),
+            UnaryKind::Asinh => self.func("asinh", "operatorname{arsinh}", x),
+            UnaryKind::Ln1p if self.latex => {
+                (format!("\\ln\\left(1 + {}\\right)", x.0), _Prec::Atom)
+            }
+            UnaryKind::Ln1p => self.func("ln_1p", "ln_1p", x),
+            UnaryKind::ExpM1 if self.latex => (format!("e^{{{}}} - 1", x.0), _Prec::Add),
+            UnaryKind::ExpM1 => self.func("exp_m1", "exp_m1", x),
+            UnaryKind::Log2 => self.func("log2", "log_{2}", x),
+            UnaryKind::Log10 => self.func("log10", "log_{10}", x),
+            UnaryKind::Exp2 if self.latex => (format!("2^{{{}}}", x.0), _Prec::Pow),
+            UnaryKind::Exp2 => self.func("exp2", "exp2", x),
+            UnaryKind::Cbrt if self.latex => (format!("\\sqrt[3]{{{}}}", x.0), _Prec::Atom),
+            UnaryKind::Cbrt => self.func("cbrt", "cbrt", x),
+            UnaryKind::Erf => self.func("erf", "operatorname{erf}", x),
+            UnaryKind::Erfc => self.func("erfc", "operatorname{erfc}", x),
+            UnaryKind::NormPdf => self.func("norm_pdf", "varphi", x),
+            UnaryKind::NormCdf => self.func("norm_cdf", "Phi", x),
+            UnaryKind::NormInvCdf => self.func("norm_inv_cdf", "Phi^{-1}", x),
+            UnaryKind::Abs(_) if self.latex => (format!("\\left|{}\\right|", x.0), _Prec::Atom),
+            UnaryKind::Abs(_) => self.func("abs", "abs", x),
+            UnaryKind::Relu(_) => self.func("relu", "operatorname{relu}", x),
+        }
+    }
+
+    fn pow(&self, base: _Term, exp: _Term) -> _Term {
+        let p = base.1 < _Prec::Atom;
+        let base = self.paren(base, p);
+        let s = match self.latex {
+            false => {
+                let p = exp.1 < _Prec::Atom;
+                format!("{}^{}", base, self.paren(exp, p))
+            }
+            true => format!("{}^{{{}}}", base, exp.0),
+        };
+        (s, _Prec::Pow)
+    }
+
+    fn binary(&self, op: BinaryKind, l: _Term, r: _Term) -> _Term {
+        // right operands which are negations are always enclosed
+        let (pl, pr) = (l.1, r.1);
+        let (sym, prec, lp, rp) = match op {
+            BinaryKind::Add => ("+", _Prec::Add, false, pr == _Prec::Neg),
+            BinaryKind::Sub => ("-", _Prec::Add, false, pr <= _Prec::Add || pr == _Prec::Neg),
+            BinaryKind::Mul if self.latex => {
+                ("\\cdot", _Prec::Mul, pl < _Prec::Mul, pr <= _Prec::Neg)
+            }
+            BinaryKind::Mul => ("*", _Prec::Mul, pl < _Prec::Mul, pr <= _Prec::Neg),
+            BinaryKind::Div if self.latex => {
+                return (format!("\\frac{{{}}}{{{}}}", l.0, r.0), _Prec::Atom);
+            }
+            BinaryKind::Div => ("/", _Prec::Mul, pl < _Prec::Mul, pr <= _Prec::Neg),
+            BinaryKind::Pow => return self.pow(l, r),
+            BinaryKind::Atan2 => return self.func2("atan2", "operatorname{atan2}", l, r),
+            BinaryKind::Max(_) => return self.func2("max", "max", l, r),
+            BinaryKind::Min(_) => return self.func2("min", "min", l, r),
+        };
+        let s = format!("{} {} {}", self.paren(l, lp), sym, self.paren(r, rp));
+        (s, prec)
+    }
+
+    fn select(&self, cmp: CmpKind, x: _Term, y: _Term, a: _Term, b: _Term) -> _Term {
+        let sym = match (cmp, self.latex) {
+            (CmpKind::Lt, _) => "<",
+            (CmpKind::Le, false) => "<=",
+            (CmpKind::Le, true) => "\\le",
+            (CmpKind::Gt, _) => ">",
+            (CmpKind::Ge, false) => ">=",
+            (CmpKind::Ge, true) => "\\ge",
+        };
+        let s = match self.latex {
+            false => format!("select({} {} {}, {}, {})", x.0, sym, y.0, a.0, b.0),
+            true => format!(
+                "\\begin{{cases}} {} & {} {} {} \\\\ {} & \\text{{otherwise}} \\end{{cases}}",
+                a.0, x.0, sym, y.0, b.0
+            ),
+        };
+        (s, _Prec::Atom)
+    }
+
+    /// Name of a temporary bound to a shared node.
+    fn temp(&self, i: usize) -> _Term {
+        let s = match self.latex {
+            false => format!("t{}", i),
+            true => format!("t_{{{}}}", i),
+        };
+        (s, _Prec::Atom)
+    }
+
+    /// Renders outputs of a graph without recursion.
+    ///
+    /// Nodes other than variables and constants used by several nodes are bound to temporaries,
+    /// e.g. `t2 = x#0 + x#1; t2 * t2`, so that formulas grow linearly with graphs.
+    /// Other rendered inputs are moved to their last user instead of being cloned.
+    fn render<T: Display>(&self, graph: &Graph<T>) -> Vec<String> {
+        let nodes = graph.nodes();
+        let mut parents = vec![0_usize; nodes.len()];
+        for i in nodes.iter().flat_map(Node::inputs) {
+            parents[i] += 1;
+        }
+        let shared: Vec<_> = nodes
+            .iter()
+            .zip(&parents)
+            .map(|(n, p)| *p > 1 && !matches!(n, Node::Var { .. } | Node::Const { .. }))
+            .collect();
+        let mut uses = parents;
+        for i in graph.outputs() {
+            uses[*i] += 1;
+        }
+        let mut terms: Vec<_Term> = Vec::with_capacity(nodes.len());
+        let mut take = |terms: &mut Vec<_Term>, i: usize| {
+            if shared[i] {
+                return self.temp(i);
+            }
+            uses[i] -= 1;
+            match uses[i] {
+                0 => std::mem::take(&mut terms[i]),
+                _ => terms[i].clone(),
+            }
+        };
+        for n in nodes {
+            let term = match n {
+                Node::Var { id, .. } => self.var(id),
+                Node::Const { o } => self.constant(o),
+                Node::Compressed { gs, .. } => {
+                    self.compressed(gs.iter().map(|(id, _)| self.var(id)).collect())
+                }
+                Node::Unary { op, i, .. } => {
+                    let x = take(&mut terms, *i);
+                    self.unary(*op, x)
+                }
+                Node::Binary { op, l, r, .. } => {
+                    let l = take(&mut terms, *l);
+                    let r = take(&mut terms, *r);
+                    self.binary(*op, l, r)
+                }
+                Node::CustomUnary { op, i, .. } => {
+                    let x = take(&mut terms, *i);
+                    let name = op.op().name();
+                    self.func(name, &format!("operatorname{{{}}}", name), x)
+                }
+                Node::CustomBinary { op, l, r, .. } => {
+                    let l = take(&mut terms, *l);
+                    let r = take(&mut terms, *r);
+                    let name = op.op().name();
+                    self.func2(name, &format!("operatorname{{{}}}", name), l, r)
+                }
+                Node::Select {
+                    cmp, x, y, a, b, ..
+                } => {
+                    let [x, y, a, b] = [x, y, a, b].map(|i| take(&mut terms, *i));
+                    self.select(*cmp, x, y, a, b)
+                }
+            };
+            terms.push(term);
+        }
+
+        let sep = match self.latex {
+            false => "; ",
+            true => ", \\quad ",
+        };
+        graph
+            .outputs()
+            .iter()
+            .map(|o| {
+                // temporaries used by the output in topological order
+                let mut visited = BTreeSet::new();
+                let mut stack = nodes[*o].inputs();
+                while let Some(i) = stack.pop() {
+                    if visited.insert(i) {
+                        stack.extend(nodes[i].inputs());
+                    }
+                }
+                let mut res = String::new();
+                for i in visited.into_iter().filter(|i| shared[*i]) {
+                    res += &format!("{} = {}{}", self.temp(i).0, terms[i].0, sep);
+                }
+                match shared[*o] {
+                    true => res += &terms[*o].0,
+                    false => res += &take(&mut terms, *o).0,
+                }
+                res
+            })
+            .collect()
+    }
+}
+
+impl Default for _Prec {
+    #[inline]
+    fn default() -> Self {
+        Self::Atom
+    }
+}
+
+impl<T: Display> Graph<T> {
+    /// Renders outputs as infix formulas.
+    ///
+    /// Shared subexpressions are bound to temporaries named by indices of nodes, e.g. `t2`.
+    pub fn to_formulas(&self) -> Vec<String> {
+        _Fmt { latex: false }.render(self)
+    }
+    /// Renders outputs as LaTeX formulas in the same way as [`Graph::to_formulas`].
+    pub fn to_latex(&self) -> Vec<String> {
+        _Fmt { latex: true }.render(self)
+    }
+}
+
+impl<T: Clone + Display> Display for Expr<T> {
+    /// Formats the recorded formula with variables named by [`Id`], e.g. `x#0 * exp(x#2)`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let formula = Graph::from(self).to_formulas().pop().unwrap();
+        f.write_str(&formula)
+    }
+}
+
+impl<T: Clone + Display> Expr<T> {
+    /// Renders the recorded formula in LaTeX.
+    pub fn to_latex(&self) -> String {
+        Graph::from(self).to_latex().pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{
+            Abs, Asin, Atan2, Cbrt, Cos, Erf, Exp, ExpM1, Ln1p, Log, Max, NormCdf, Powf, Powi, Sqrt,
+        },
+        VarGroup,
+    };
+
+    #[test]
+    fn test_display() {
+        let grp = VarGroup::new("x");
+        let x: Vec<_> = [1.0, 2.0, 3.0].map(|v| grp.val(v).into_expr()).into();
+
+        assert_eq!(
+            (x[0].clone() * x[2].clone().exp()).to_string(),
+            "x#0 * exp(x#2)"
+        );
+        let e = (x[0].clone() + &x[1]) * (x[1].clone() - &x[2]) / (x[2].clone() * &x[0]);
+        assert_eq!(e.to_string(), "(x#0 + x#1) * (x#1 - x#2) / (x#2 * x#0)");
+        let e = x[0].clone() - (x[1].clone() - &x[2]) + (x[1].clone() - &x[2]) * 2.0;
+        assert_eq!(e.to_string(), "x#0 - (x#1 - x#2) + (x#1 - x#2) * 2");
+        let e = -(-x[0].clone()) * -(x[1].clone() + 1.0) - -1.0;
+        assert_eq!(e.to_string(), "-(-x#0) * (-(x#1 + 1)) - (-1)");
+        let e = -(x[0].clone() + 1.0).powi(-2) * x[1].clone().powf(x[2].clone().powf(2.0));
+        assert_eq!(e.to_string(), "-(x#0 + 1)^(-2) * x#1^(x#2^2)");
+        assert_eq!(
+            e.to_latex(),
+            "-\\left(\\mathrm{x}_{0} + 1\\right)^{-2} \\cdot \\mathrm{x}_{1}^{\\mathrm{x}_{2}^{2}}"
+        );
+        let e = x[0].clone().asin().cos() + x[1].clone().atan2(x[2].clone());
+        assert_eq!(e.to_string(), "cos(asin(x#0)) + atan2(x#1, x#2)");
+        assert_eq!(
+            e.to_latex(),
+            "\\cos\\left(\\arcsin\\left(\\mathrm{x}_{0}\\right)\\right) + \\operatorname{atan2}\\left(\\mathrm{x}_{1}, \\mathrm{x}_{2}\\right)"
+        );
+        let e = x[0].clone().ln_1p() * x[1].clone().exp_m1() / x[2].clone().cbrt();
+        assert_eq!(e.to_string(), "ln_1p(x#0) * exp_m1(x#1) / cbrt(x#2)");
+        assert_eq!(
+            e.to_latex(),
+            "\\frac{\\ln\\left(1 + \\mathrm{x}_{0}\\right) \\cdot \\left(e^{\\mathrm{x}_{1}} - 1\\right)}{\\sqrt[3]{\\mathrm{x}_{2}}}"
+        );
+        let e = x[0].clone().norm_cdf() - x[1].clone().erf();
+        assert_eq!(e.to_string(), "norm_cdf(x#0) - erf(x#1)");
+        assert_eq!(
+            e.to_latex(),
+            "\\Phi\\left(\\mathrm{x}_{0}\\right) - \\operatorname{erf}\\left(\\mathrm{x}_{1}\\right)"
+        );
+        let e = x[0].clone().abs().max(x[1].clone());
+        assert_eq!(e.to_string(), "max(abs(x#0), x#1)");
+        assert_eq!(
+            e.to_latex(),
+            "\\max\\left(\\left|\\mathrm{x}_{0}\\right|, \\mathrm{x}_{1}\\right)"
+        );
+        let e = (x[0].clone() / (x[1].clone() * &x[2])).sqrt().log();
+        assert_eq!(e.to_string(), "log(sqrt(x#0 / (x#1 * x#2)))");
+        assert_eq!(
+            e.to_latex(),
+            "\\ln\\left(\\sqrt{\\frac{\\mathrm{x}_{0}}{\\mathrm{x}_{1} \\cdot \\mathrm{x}_{2}}}\\right)"
+        );
+    }
+
+    #[test]
+    fn test_display_deep() {
+        let grp = VarGroup::new("x");
+        let mut e = grp.val(1.0_f64).into_expr();
+        for _ in 0..100000 {
+            e = e.exp();
+        }
+        let s = e.to_string();
+        assert!(s.starts_with("exp(exp(") && s.contains("exp(exp(x#0))))"));
+        assert_eq!(s.len(), 5 * 100000 + 3);
+    }
+
+    #[test]
+    fn test_display_shared() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(1.0_f64).into_expr();
+        let mut y = x.clone();
+        for _ in 0..3 {
+            y = (y.clone() + &y) * 0.5;
+        }
+        assert_eq!(
+            y.to_string(),
+            "t1 = x#0 + x#0; t6 = t1 * 0.5 + t1 * 0.5; (t6 * 0.5 + t6 * 0.5) * 0.5"
+        );
+        assert_eq!(
+            y.to_latex(),
+            "t_{1} = \\mathrm{x}_{0} + \\mathrm{x}_{0}, \\quad t_{6} = t_{1} \\cdot 0.5 + t_{1} \\cdot 0.5, \\quad \\left(t_{6} \\cdot 0.5 + t_{6} \\cdot 0.5\\right) \\cdot 0.5"
+        );
+
+        // formulas grow linearly rather than exponentially
+        for _ in 0..197 {
+            y = (y.clone() + &y) * 0.5;
+        }
+        assert!(y.to_string().len() < 10000);
+    }
+}
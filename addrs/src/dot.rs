@@ -0,0 +1,268 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Write},
+};
+
+use crate::{
+    graph::{Graph, Node},
+    Id,
+};
+
+/// Options of the Graphviz DOT export.
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    max_depth: Option<usize>,
+    collapse_compressed: bool,
+}
+
+impl DotOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Nodes farther than `depth` from outputs are omitted.
+    #[inline]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+    /// Draws compressed nodes without edges to variables they depend on.
+    #[inline]
+    pub fn collapse_compressed(mut self, collapse: bool) -> Self {
+        self.collapse_compressed = collapse;
+        self
+    }
+}
+
+/// Joins lines of a label escaping special characters.
+fn _label(lines: &[String]) -> String {
+    let lines: Vec<_> = lines
+        .iter()
+        .map(|s| s.replace('\\', "\\\\").replace('"', "\\\""))
+        .collect();
+    lines.join("\\n")
+}
+
+#[inline]
+fn _id_lines(id: &Id) -> Vec<String> {
+    vec![
+        format!("{}#{}", id.name, id.num),
+        format!("group = {}", id.group),
+    ]
+}
+
+impl<T: Debug> Graph<T> {
+    /// Emits a graph in the Graphviz DOT language.
+    ///
+    /// Edges are directed from inputs to nodes using them.
+    pub fn to_dot(&self, opts: &DotOptions) -> String {
+        let nodes = self.nodes();
+
+        // distance from the nearest output and generation of each node
+        let mut depths: Vec<Option<usize>> = vec![None; nodes.len()];
+        for i in self.outputs() {
+            depths[*i] = Some(0);
+        }
+        for (i, n) in nodes.iter().enumerate().rev() {
+            let Some(d) = depths[i] else {
+                continue;
+            };
+            for j in n.inputs() {
+                depths[j] = Some(depths[j].map_or(d + 1, |x| x.min(d + 1)));
+            }
+        }
+        let mut gens = vec![0; nodes.len()];
+        for (i, n) in nodes.iter().enumerate() {
+            gens[i] = match n {
+                Node::Compressed { g, .. } => *g,
+                _ => n.inputs().iter().map(|j| gens[*j] + 1).max().unwrap_or(0),
+            };
+        }
+        let visible = |i: usize| match (depths[i], opts.max_depth) {
+            (Some(d), Some(max)) => d <= max,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        let is_cut = |i: usize| opts.max_depth.is_some_and(|max| depths[i] == Some(max));
+
+        let mut vars = BTreeMap::new();
+        for (i, n) in nodes.iter().enumerate() {
+            if let Node::Var { id, .. } = n {
+                vars.insert(id, i);
+            }
+        }
+
+        let mut res = String::from("digraph {\n");
+        let mut extra = BTreeMap::new();
+        for (i, n) in nodes.iter().enumerate() {
+            if !visible(i) {
+                continue;
+            }
+            let (lines, shape) = match n {
+                Node::Var { id, o } => {
+                    let mut lines = _id_lines(id);
+                    lines.push(format!("o = {:?}", o));
+                    (lines, "box")
+                }
+                Node::Const { o } => (vec!["const".into(), format!("o = {:?}", o)], "plaintext"),
+                Node::CustomUnary { op, o, g, .. } => (
+                    vec![
+                        op.op().name().to_string(),
+                        format!("o = {:?}", o),
+                        format!("g = {:?}", g),
+                        format!("gen = {}", gens[i]),
+                    ],
+                    "ellipse",
+                ),
+                Node::CustomBinary { op, o, gl, gr, .. } => (
+                    vec![
+                        op.op().name().to_string(),
+                        format!("o = {:?}", o),
+                        format!("gl = {:?}", gl),
+                        format!("gr = {:?}", gr),
+                        format!("gen = {}", gens[i]),
+                    ],
+                    "ellipse",
+                ),
+                Node::Unary { op, o, g, .. } => (
+                    vec![
+                        format!("{:?}", op),
+                        format!("o = {:?}", o),
+                        format!("g = {:?}", g),
+                        format!("gen = {}", gens[i]),
+                    ],
+                    "ellipse",
+                ),
+                Node::Binary { op, o, gl, gr, .. } => (
+                    vec![
+                        format!("{:?}", op),
+                        format!("o = {:?}", o),
+                        format!("gl = {:?}", gl),
+                        format!("gr = {:?}", gr),
+                        format!("gen = {}", gens[i]),
+                    ],
+                    "ellipse",
+                ),
+                Node::Compressed { o, gs, .. } => (
+                    vec![
+                        "Compressed".into(),
+                        format!("o = {:?}", o),
+                        format!("vars = {}", gs.len()),
+                        format!("gen = {}", gens[i]),
+                    ],
+                    "doubleoctagon",
+                ),
+                Node::Select { cmp, o, cond, .. } => (
+                    vec![
+                        format!("Select({:?})", cmp),
+                        format!("o = {:?}", o),
+                        format!("cond = {}", cond),
+                        format!("gen = {}", gens[i]),
+                    ],
+                    "diamond",
+                ),
+            };
+            writeln!(
+                res,
+                "    n{} [label=\"{}\", shape={}];",
+                i,
+                _label(&lines),
+                shape
+            )
+            .unwrap();
+
+            let has_inputs = match n {
+                Node::Compressed { gs, .. } => !opts.collapse_compressed && !gs.is_empty(),
+                _ => !n.inputs().is_empty(),
+            };
+            if has_inputs && is_cut(i) {
+                writeln!(res, "    c{} [label=\"...\", shape=plaintext];", i).unwrap();
+                writeln!(res, "    c{} -> n{} [style=dashed];", i, i).unwrap();
+                continue;
+            }
+            match n {
+                Node::Unary { i: j, .. } | Node::CustomUnary { i: j, .. } => {
+                    writeln!(res, "    n{} -> n{};", j, i).unwrap()
+                }
+                Node::Binary { l, r, .. } | Node::CustomBinary { l, r, .. } => {
+                    writeln!(res, "    n{} -> n{} [label=\"l\"];", l, i).unwrap();
+                    writeln!(res, "    n{} -> n{} [label=\"r\"];", r, i).unwrap();
+                }
+                Node::Select {
+                    x, y, a, b, cond, ..
+                } => {
+                    writeln!(res, "    n{} -> n{} [label=\"x\"];", x, i).unwrap();
+                    writeln!(res, "    n{} -> n{} [label=\"y\"];", y, i).unwrap();
+                    // the branch not taken is dashed
+                    let (sa, sb) = if *cond {
+                        ("", ", style=dashed")
+                    } else {
+                        (", style=dashed", "")
+                    };
+                    writeln!(res, "    n{} -> n{} [label=\"a\"{}];", a, i, sa).unwrap();
+                    writeln!(res, "    n{} -> n{} [label=\"b\"{}];", b, i, sb).unwrap();
+                }
+                Node::Compressed { gs, .. } if !opts.collapse_compressed => {
+                    for (id, g) in gs {
+                        let from = match vars.get(id) {
+                            Some(j) if visible(*j) => format!("n{}", j),
+                            _ => {
+                                let len = extra.len();
+                                let v = extra.entry(id).or_insert_with(|| format!("v{}", len));
+                                v.clone()
+                            }
+                        };
+                        let g = _label(&[format!("{:?}", g)]);
+                        writeln!(
+                            res,
+                            "    {} -> n{} [label=\"{}\", style=dotted];",
+                            from, i, g
+                        )
+                        .unwrap();
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (id, name) in extra {
+            let label = _label(&_id_lines(id));
+            writeln!(res, "    {} [label=\"{}\", shape=box];", name, label).unwrap();
+        }
+        res.push_str("}\n");
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scalar::Exp, VarGroup};
+
+    use super::*;
+
+    #[test]
+    fn test_to_dot() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.5_f64).into_expr();
+        let y = grp.val(2.0_f64).into_expr();
+        let mut c = x.clone() * &y;
+        c.compress();
+        let e = (x.clone() * &y).exp();
+        let z = e.clone() * &e + c - 1.0;
+
+        let dot = z.to_dot();
+        assert!(dot.starts_with("digraph {") && dot.ends_with("}\n"));
+        for label in ["Exp", "Mul", "Add", "Sub", "Compressed", "x#0", "x#1"] {
+            assert!(dot.contains(label), "{}", label);
+        }
+        // `x * y` is shared by both copies of `e` and appears once
+        assert_eq!(dot.matches("Mul\\n").count(), 2);
+        assert_eq!(dot.matches("style=dotted").count(), 2);
+
+        let collapsed = z.to_dot_with(&DotOptions::new().collapse_compressed(true));
+        assert!(!collapsed.contains("style=dotted"));
+
+        let cut = z.to_dot_with(&DotOptions::new().max_depth(1));
+        assert!(cut.contains("..."));
+        assert!(!cut.contains("Exp"));
+    }
+}
@@ -1,9 +1,17 @@
-use std::collections::{BTreeMap, VecDeque};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+};
 
 use derivative::Derivative;
 use num_traits::{One, Zero};
 
-use crate::{private::_Expr, scalar::Scalar, var::Id, Var};
+use crate::{
+    dot::DotOptions,
+    private::{_Dense, _Expr},
+    scalar::Scalar,
+    Gradients, Graph, Jacobian, Var, VarGroup,
+};
 
 #[derive(Clone, Derivative)]
 #[derivative(Debug = "transparent")]
@@ -41,14 +49,27 @@ impl<T> Expr<T> {
     pub fn as_var(&self) -> Option<&Var<T>> {
         self.0.as_var()
     }
+    /// Depth of the graph, i.e. the length of the longest path to leaves.
+    #[inline]
+    pub fn generation(&self) -> usize {
+        self.0.generation()
+    }
     #[inline]
     pub(crate) fn _take(mut self) -> _Expr<T> {
         std::mem::replace(&mut self.0, _Expr::_OnlyForDrop)
     }
     #[inline]
+    pub(crate) fn _ref(&self) -> &_Expr<T> {
+        &self.0
+    }
+    #[inline]
     pub(crate) fn _is_const(&self) -> bool {
         self.0.is_const()
     }
+    #[inline]
+    pub(crate) fn _is_taped(&self) -> bool {
+        self.0.is_taped()
+    }
 }
 
 impl<T: Scalar> Expr<T> {
@@ -57,17 +78,78 @@ impl<T: Scalar> Expr<T> {
         let compressed = _Expr::Compressed {
             g: self.0.generation(),
             o: self.output().clone(),
-            gs: self.grads(),
+            gs: self.grads().into_shared(),
         };
         *self = compressed.into();
     }
+    /// Derivatives of compressed expressions are shared without copying.
+    #[inline]
+    pub fn grads(&self) -> Gradients<T> {
+        match &self.0 {
+            _Expr::Compressed { gs, .. } => Gradients::from_shared(gs.clone()),
+            _ => self.grads_with_seed(T::one()),
+        }
+    }
     #[inline]
-    pub fn grads(&self) -> BTreeMap<Id, T> {
-        self.grads_with_seed(T::one())
+    pub fn grads_with_seed(&self, seed: T) -> Gradients<T> {
+        self.0.grads(seed).into()
+    }
+    /// Calculates derivatives by variables in `grp`, indexed by their numbers.
+    ///
+    /// Derivatives by variables in other groups are ignored.
+    pub fn grads_dense(&self, grp: &VarGroup) -> Vec<T> {
+        let mut res = vec![T::zero(); grp.len()];
+        self.grads_dense_into(grp, &mut res);
+        res
+    }
+    /// Same as [`grads_dense`](Self::grads_dense) but writes into a reusable buffer.
+    ///
+    /// # Panics
+    /// Panics if `buf` is shorter than the number of variables in `grp`.
+    pub fn grads_dense_into(&self, grp: &VarGroup, buf: &mut [T]) {
+        assert!(buf.len() >= grp.len(), "buffer is too short");
+        buf.iter_mut().for_each(|x| *x = T::zero());
+        let mut res = _Dense {
+            group: grp.id(),
+            buf,
+        };
+        _Expr::backward([(&self.0, T::one())], &mut res);
     }
+    /// Calculates derivatives of the weighted sum of several outputs with one reverse sweep.
+    ///
+    /// This is equivalent to, but cheaper than, summing up `grads_with_seed` of each output.
+    ///
+    /// # Panics
+    /// Panics if lengths of `outputs` and `seeds` are different.
+    pub fn vjp(outputs: &[Self], seeds: &[T]) -> Gradients<T> {
+        assert_eq!(
+            outputs.len(),
+            seeds.len(),
+            "number of seeds must be the same as that of outputs"
+        );
+        let roots = outputs.iter().map(|e| &e.0).zip(seeds.iter().cloned());
+        let mut res = BTreeMap::new();
+        _Expr::backward(roots, &mut res);
+        res.into()
+    }
+    /// Calculates derivatives of several outputs with one reverse sweep.
+    ///
+    /// Nodes shared by outputs are visited only once,
+    /// propagating adjoints of all outputs depending on them together.
     #[inline]
-    pub fn grads_with_seed(&self, seed: T) -> BTreeMap<Id, T> {
-        self.0.grads(seed)
+    pub fn jacobian(outputs: &[Self]) -> Jacobian<T> {
+        Jacobian::new(outputs)
+    }
+}
+impl<T: Clone + Debug> Expr<T> {
+    /// Emits the computation graph in the Graphviz DOT language.
+    #[inline]
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+    #[inline]
+    pub fn to_dot_with(&self, opts: &DotOptions) -> String {
+        Graph::from(self).to_dot(opts)
     }
 }
 impl<T> AsRef<T> for Expr<T> {
@@ -127,6 +209,7 @@ impl<T> Drop for Expr<T> {
         if matches!(self.0, _Expr::Leaf(..))
             || matches!(self.0, _Expr::_OnlyForDrop)
             || matches!(self.0, _Expr::Compressed { .. })
+            || matches!(self.0, _Expr::Taped(_))
         {
             return;
         }
@@ -141,3 +224,58 @@ impl<T> Drop for Expr<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{scalar::Exp, Id};
+
+    use super::*;
+
+    #[test]
+    fn test_grads_dense() {
+        let grp = VarGroup::new("x");
+        let other = VarGroup::new("y");
+        let x: Vec<_> = (0..4)
+            .map(|i| grp.val(i as f64 + 1.0).into_expr())
+            .collect();
+        let y = other.val(2.0).into_expr();
+        let mut c = x[3].clone() * &y;
+        c.compress();
+        let e = (x[0].clone() * &x[2]).exp() * &y + c + 1.0;
+
+        let expected = e.grads();
+        let actual = e.grads_dense(&grp);
+        assert_eq!(actual.len(), 4);
+        for (x, actual) in x.iter().zip(&actual) {
+            let expected = expected.get(x.as_var().unwrap().id()).copied();
+            assert_eq!(*actual, expected.unwrap_or(0.0));
+        }
+
+        let mut buf = vec![f64::NAN; 5];
+        e.grads_dense_into(&grp, &mut buf);
+        assert_eq!(&buf[..4], &actual[..]);
+        assert_eq!(buf[4], 0.0);
+    }
+
+    #[test]
+    fn test_vjp() {
+        let grp = VarGroup::new("x");
+        let r = grp.val(0.03_f64).into_expr();
+        let s = grp.val(0.01_f64).into_expr();
+        let df = (-(r.clone() + &s) * 2.0).exp();
+        let trades = [df.clone() * 100.0, df.clone() * &r, df * &s + &r];
+        let positions = [2.0, -1.0, 0.5];
+
+        let actual = Expr::vjp(&trades, &positions);
+        let mut expected = BTreeMap::<Id, f64>::new();
+        for (trade, pos) in trades.iter().zip(positions) {
+            for (id, g) in trade.grads_with_seed(pos) {
+                *expected.entry(id).or_default() += g;
+            }
+        }
+        assert_eq!(actual.len(), expected.len());
+        for (id, g) in expected {
+            assert!((actual[&id] - g).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    ops::{Add, Index, Mul, Neg, Sub},
+};
+
+use crate::{private::_Rc, scalar::Scalar, Expr, Id, Var, VarGroup};
+
+/// Keys to look up [`Gradients`].
+pub trait AsId {
+    /// Returns `None` if this is not a variable.
+    fn as_id(&self) -> Option<&Id>;
+}
+
+impl AsId for Id {
+    #[inline]
+    fn as_id(&self) -> Option<&Id> {
+        Some(self)
+    }
+}
+impl<T> AsId for Var<T> {
+    #[inline]
+    fn as_id(&self) -> Option<&Id> {
+        Some(self.id())
+    }
+}
+impl<T> AsId for Expr<T> {
+    #[inline]
+    fn as_id(&self) -> Option<&Id> {
+        self.as_var().map(Var::id)
+    }
+}
+
+/// Derivatives of an expression keyed by variables.
+///
+/// Entries are sorted by [`Id`].
+/// Cloning is cheap since the storage is shared,
+/// for example with the compressed expression it came from.
+#[derive(Debug, Clone)]
+pub struct Gradients<T>(_Rc<BTreeMap<Id, T>>);
+
+impl<T> Gradients<T> {
+    #[inline]
+    pub(crate) fn from_shared(gs: _Rc<BTreeMap<Id, T>>) -> Self {
+        Self(gs)
+    }
+    #[inline]
+    pub(crate) fn into_shared(self) -> _Rc<BTreeMap<Id, T>> {
+        self.0
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Returns `None` if the expression does not depend on `key`.
+    #[inline]
+    pub fn get(&self, key: &impl AsId) -> Option<&T> {
+        self.0.get(key.as_id()?)
+    }
+    #[inline]
+    pub fn contains(&self, key: &impl AsId) -> bool {
+        self.get(key).is_some()
+    }
+    #[inline]
+    pub fn iter(&self) -> btree_map::Iter<'_, Id, T> {
+        self.0.iter()
+    }
+    #[inline]
+    pub fn ids(&self) -> btree_map::Keys<'_, Id, T> {
+        self.0.keys()
+    }
+    #[inline]
+    pub fn values(&self) -> btree_map::Values<'_, Id, T> {
+        self.0.values()
+    }
+    #[inline]
+    pub fn as_map(&self) -> &BTreeMap<Id, T> {
+        &self.0
+    }
+}
+
+impl<T: Clone> Gradients<T> {
+    /// Storage is cloned only if it is shared.
+    #[inline]
+    pub fn into_map(self) -> BTreeMap<Id, T> {
+        _Rc::try_unwrap(self.0).unwrap_or_else(|gs| (*gs).clone())
+    }
+    /// Derivatives by variables in `grp`.
+    pub fn filter_group(&self, grp: &VarGroup) -> Self {
+        let gs = self.iter().filter(|(id, _)| id.group == grp.id());
+        gs.map(|(id, g)| (id.clone(), g.clone())).collect()
+    }
+}
+
+impl<T: Scalar + PartialOrd> Gradients<T> {
+    /// Drops entries whose absolute values are less than `threshold`.
+    pub fn pruned(&self, threshold: &T) -> Self {
+        let gs = self
+            .iter()
+            .filter(|(_, g)| *g >= threshold || -(*g).clone() >= *threshold);
+        gs.map(|(id, g)| (id.clone(), g.clone())).collect()
+    }
+}
+
+impl<T: Scalar> Gradients<T> {
+    /// Multiplies all entries by `k`.
+    pub fn scaled(&self, k: &T) -> Self {
+        let gs = self.iter().map(|(id, g)| (id.clone(), g.clone() * k));
+        gs.collect()
+    }
+}
+
+impl<T> Default for Gradients<T> {
+    #[inline]
+    fn default() -> Self {
+        Self(_Rc::new(BTreeMap::new()))
+    }
+}
+
+impl<T> From<BTreeMap<Id, T>> for Gradients<T> {
+    #[inline]
+    fn from(gs: BTreeMap<Id, T>) -> Self {
+        Self(_Rc::new(gs))
+    }
+}
+impl<T: Clone> From<Gradients<T>> for BTreeMap<Id, T> {
+    #[inline]
+    fn from(gs: Gradients<T>) -> Self {
+        gs.into_map()
+    }
+}
+impl<T> FromIterator<(Id, T)> for Gradients<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (Id, T)>>(iter: I) -> Self {
+        Self::from(BTreeMap::from_iter(iter))
+    }
+}
+
+impl<T: Clone> IntoIterator for Gradients<T> {
+    type Item = (Id, T);
+    type IntoIter = btree_map::IntoIter<Id, T>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_map().into_iter()
+    }
+}
+impl<'a, T> IntoIterator for &'a Gradients<T> {
+    type Item = (&'a Id, &'a T);
+    type IntoIter = btree_map::Iter<'a, Id, T>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, K: AsId> Index<&K> for Gradients<T> {
+    type Output = T;
+    /// # Panics
+    /// Panics if the expression does not depend on `key`.
+    #[inline]
+    fn index(&self, key: &K) -> &T {
+        self.get(key).expect("no derivative by the key")
+    }
+}
+
+impl<T: PartialEq> PartialEq for Gradients<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Scalar> Add<&Gradients<T>> for &Gradients<T> {
+    type Output = Gradients<T>;
+    fn add(self, rhs: &Gradients<T>) -> Self::Output {
+        let mut res = self.as_map().clone();
+        for (id, g) in rhs {
+            match res.get_mut(id) {
+                Some(x) => *x += g,
+                None => {
+                    res.insert(id.clone(), g.clone());
+                }
+            }
+        }
+        res.into()
+    }
+}
+impl<T: Scalar> Add for Gradients<T> {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+impl<T: Scalar> Sub<&Gradients<T>> for &Gradients<T> {
+    type Output = Gradients<T>;
+    #[inline]
+    fn sub(self, rhs: &Gradients<T>) -> Self::Output {
+        self + &-rhs
+    }
+}
+impl<T: Scalar> Sub for Gradients<T> {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+impl<T: Scalar> Neg for &Gradients<T> {
+    type Output = Gradients<T>;
+    #[inline]
+    fn neg(self) -> Self::Output {
+        self.iter()
+            .map(|(id, g)| (id.clone(), -g.clone()))
+            .collect()
+    }
+}
+impl<T: Scalar> Neg for Gradients<T> {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+impl<T: Scalar> Mul<T> for &Gradients<T> {
+    type Output = Gradients<T>;
+    #[inline]
+    fn mul(self, rhs: T) -> Self::Output {
+        self.scaled(&rhs)
+    }
+}
+impl<T: Scalar> Mul<T> for Gradients<T> {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: T) -> Self::Output {
+        self.scaled(&rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scalar::Exp;
+
+    use super::*;
+
+    #[test]
+    fn test_gradients() {
+        let grp = VarGroup::new("x");
+        let other = VarGroup::new("y");
+        let x = grp.val(0.5_f64).into_expr();
+        let y = other.val(2.0).into_expr();
+        let a = (x.clone() * &y).exp();
+        let b = x.clone() * 1e-9 + &y;
+
+        let (ga, gb) = (a.grads(), b.grads());
+        assert_eq!(ga[&x], a.output() * 2.0);
+        assert_eq!(ga.get(y.as_var().unwrap()), Some(&(a.output() * 0.5)));
+        assert_eq!(ga.get(&Expr::constant(1.0)), None);
+
+        let total = &ga * 2.0 + gb.clone();
+        assert_eq!(total[&x], ga[&x] * 2.0 + 1e-9);
+        assert_eq!(total[&y], ga[&y] * 2.0 + 1.0);
+        assert_eq!((&total - &total).values().sum::<f64>(), 0.0);
+
+        let filtered = total.filter_group(&grp);
+        assert_eq!(
+            filtered.ids().collect::<Vec<_>>(),
+            vec![x.as_var().unwrap().id()]
+        );
+        let pruned = gb.pruned(&1e-6);
+        assert!(!pruned.contains(&x) && pruned.contains(&y));
+    }
+
+    #[test]
+    fn test_gradients_shared() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.5_f64).into_expr();
+        let mut c = x.clone().exp() * &x;
+        c.compress();
+        let (g1, g2) = (c.grads(), c.grads());
+        assert!(std::ptr::eq(g1.as_map(), g2.as_map()));
+        assert_eq!(g1.into_map(), g2.as_map().clone());
+    }
+}
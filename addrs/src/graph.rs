@@ -0,0 +1,414 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    custom::{CustomBinary, CustomUnary},
+    private::_Graph,
+    scalar::Subgradient,
+    Expr, Id, VarGroup,
+};
+
+/// Kind of a unary operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryKind {
+    Neg,
+    Sqrt,
+    Exp,
+    Log,
+    Powi(i32),
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Tanh,
+    Sinh,
+    Cosh,
+    Asinh,
+    Ln1p,
+    ExpM1,
+    Log2,
+    Log10,
+    Exp2,
+    Cbrt,
+    Erf,
+    Erfc,
+    NormPdf,
+    NormCdf,
+    NormInvCdf,
+    Abs(Subgradient),
+    Relu(Subgradient),
+}
+
+/// Kind of a binary operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinaryKind {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Atan2,
+    Max(Subgradient),
+    Min(Subgradient),
+}
+
+/// Comparison deciding the branch of a select node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CmpKind {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpKind {
+    /// Comparisons involving NaN are false.
+    #[inline]
+    pub fn eval<T: PartialOrd>(&self, x: &T, y: &T) -> bool {
+        match self {
+            Self::Lt => x < y,
+            Self::Le => x <= y,
+            Self::Gt => x > y,
+            Self::Ge => x >= y,
+        }
+    }
+}
+
+/// Node of a [`Graph`].
+///
+/// Inputs are referred by indices of nodes in the graph.
+/// `o` is the output value and `g`, `gl` and `gr` are local partial derivatives.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Node<T> {
+    Var {
+        id: Id,
+        o: T,
+    },
+    Const {
+        o: T,
+    },
+    Unary {
+        op: UnaryKind,
+        i: usize,
+        o: T,
+        g: T,
+    },
+    Binary {
+        op: BinaryKind,
+        l: usize,
+        r: usize,
+        o: T,
+        gl: T,
+        gr: T,
+    },
+    Compressed {
+        g: usize,
+        o: T,
+        gs: Vec<(Id, T)>,
+    },
+    /// User-defined operations cannot be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    CustomUnary {
+        op: CustomUnary<T>,
+        i: usize,
+        o: T,
+        g: T,
+    },
+    #[cfg_attr(feature = "serde", serde(skip))]
+    CustomBinary {
+        op: CustomBinary<T>,
+        l: usize,
+        r: usize,
+        o: T,
+        gl: T,
+        gr: T,
+    },
+    /// `a` if `cmp` holds for `(x, y)` and `b` otherwise.
+    /// `cond` records the branch taken, through which alone adjoints flow.
+    Select {
+        cmp: CmpKind,
+        x: usize,
+        y: usize,
+        a: usize,
+        b: usize,
+        o: T,
+        cond: bool,
+    },
+}
+
+impl<T> Node<T> {
+    #[inline]
+    pub fn output(&self) -> &T {
+        match self {
+            Self::Var { o, .. }
+            | Self::Const { o }
+            | Self::Unary { o, .. }
+            | Self::Binary { o, .. }
+            | Self::Compressed { o, .. }
+            | Self::Select { o, .. }
+            | Self::CustomUnary { o, .. }
+            | Self::CustomBinary { o, .. } => o,
+        }
+    }
+    /// Indices of input nodes.
+    #[inline]
+    pub fn inputs(&self) -> Vec<usize> {
+        match self {
+            Self::Unary { i, .. } | Self::CustomUnary { i, .. } => vec![*i],
+            Self::Binary { l, r, .. } | Self::CustomBinary { l, r, .. } => vec![*l, *r],
+            Self::Select { x, y, a, b, .. } => vec![*x, *y, *a, *b],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Flat representation of expressions.
+///
+/// Nodes are sorted topologically, i.e. inputs of a node always precede the node itself,
+/// and nodes shared in the original expressions appear only once.
+/// With the `serde` feature, this can be serialized to persist expressions.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Graph<T> {
+    nodes: Vec<Node<T>>,
+    outputs: Vec<usize>,
+}
+
+impl<T> Graph<T> {
+    #[inline]
+    pub fn nodes(&self) -> &[Node<T>] {
+        &self.nodes
+    }
+    /// Indices of nodes corresponding to the original expressions.
+    #[inline]
+    pub fn outputs(&self) -> &[usize] {
+        &self.outputs
+    }
+    /// Replaces groups of variables with fresh ones,
+    /// which are returned keyed by ids of the replaced groups.
+    ///
+    /// Group ids are only unique within a process,
+    /// so those of a graph loaded from elsewhere may collide with groups created here
+    /// and derivatives by unrelated variables would be merged.
+    /// Variables created later in the returned groups are numbered after restored ones.
+    pub fn renew_groups(&mut self) -> BTreeMap<usize, VarGroup> {
+        let mut groups = BTreeMap::new();
+        let mut renew = |id: &mut Id| {
+            let grp = groups
+                .entry(id.group)
+                .or_insert_with(|| VarGroup::new(id.name.clone()));
+            grp.reserve(id.num + 1);
+            id.group = grp.id();
+        };
+        for n in &mut self.nodes {
+            match n {
+                Node::Var { id, .. } => renew(id),
+                Node::Compressed { gs, .. } => gs.iter_mut().for_each(|(id, _)| renew(id)),
+                _ => {}
+            }
+        }
+        groups
+    }
+    #[inline]
+    pub(crate) fn nodes_mut(&mut self) -> &mut [Node<T>] {
+        &mut self.nodes
+    }
+    /// Checks that inputs of nodes precede them and outputs are in range.
+    pub(crate) fn check(&self) -> Result<(), GraphError> {
+        for (node, n) in self.nodes.iter().enumerate() {
+            if let Some(input) = n.inputs().into_iter().find(|i| *i >= node) {
+                return Err(GraphError::InvalidInput { node, input });
+            }
+        }
+        match self.outputs.iter().find(|i| **i >= self.nodes.len()) {
+            Some(i) => Err(GraphError::InvalidOutput(*i)),
+            None => Ok(()),
+        }
+    }
+    #[inline]
+    pub(crate) fn from_parts(nodes: Vec<Node<T>>, outputs: Vec<usize>) -> Self {
+        Self { nodes, outputs }
+    }
+}
+
+impl<T: Clone> Graph<T> {
+    /// Flattens expressions.
+    pub fn new(exprs: &[Expr<T>]) -> Self {
+        _Graph::flatten(exprs.iter().map(|e| e._ref()))
+    }
+    /// Restores expressions.
+    /// Stored outputs and partial derivatives are used as they are.
+    ///
+    /// Variables keep their ids, so a graph loaded from another process
+    /// should be passed to [`Graph::renew_groups`] first.
+    pub fn to_exprs(&self) -> Result<Vec<Expr<T>>, GraphError> {
+        _Graph::restore(self)
+    }
+}
+
+impl<T: Clone> From<&Expr<T>> for Graph<T> {
+    #[inline]
+    fn from(e: &Expr<T>) -> Self {
+        Self::new(std::slice::from_ref(e))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    /// Input of a node does not precede the node.
+    InvalidInput { node: usize, input: usize },
+    /// Output is out of range.
+    InvalidOutput(usize),
+}
+
+impl Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidInput { node, input } => {
+                write!(f, "input {} of node {} does not precede it", input, node)
+            }
+            Self::InvalidOutput(i) => write!(f, "output {} is out of range", i),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{Exp, Log, Sqrt},
+        Tape, VarGroup,
+    };
+
+    use super::*;
+
+    fn sample() -> (Expr<f64>, Expr<f64>) {
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.3).into_expr();
+        let y = grp.val(1.2).into_expr();
+        let mut z = grp.val(2.5).into_expr() * &y;
+        z.compress();
+        let df = (-x.clone() * &y).exp();
+        let res = (df.clone() * &z + z.clone().sqrt() / &df - y.log()) * 2.0 - 1.0;
+
+        let tape = Tape::new();
+        let x = tape.var(grp.val(0.7));
+        let taped = (x.clone() * &df).exp() + 1.0 - x;
+        (res, taped)
+    }
+
+    fn assert_same(actual: &Expr<f64>, expected: &Expr<f64>) {
+        assert_eq!(actual.output(), expected.output());
+        let (actual, expected) = (actual.grads(), expected.grads());
+        assert_eq!(actual.len(), expected.len());
+        for (id, g) in expected {
+            assert!((actual[&id] - g).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_graph_roundtrip() {
+        let (res, taped) = sample();
+        let graph = Graph::new(&[res.clone(), taped.clone()]);
+        let nodes = graph.nodes();
+
+        // variables appear only once
+        let vars = nodes.iter().filter(|n| matches!(n, Node::Var { .. }));
+        assert_eq!(vars.count(), 3);
+        // `df` is shared by the tape and the tree
+        let df = nodes.iter().filter(|n| match n {
+            Node::Unary { op, i, .. } => *op == UnaryKind::Neg && nodes[*i].inputs().is_empty(),
+            _ => false,
+        });
+        assert_eq!(df.count(), 1);
+        for (i, n) in nodes.iter().enumerate() {
+            assert!(n.inputs().iter().all(|j| *j < i));
+        }
+
+        let exprs = graph.to_exprs().unwrap();
+        assert_same(&exprs[0], &res);
+        assert_same(&exprs[1], &taped);
+    }
+
+    #[test]
+    fn test_graph_invalid() {
+        let graph = Graph::from_parts(
+            vec![Node::Unary {
+                op: UnaryKind::Exp,
+                i: 0,
+                o: 1.0,
+                g: 1.0,
+            }],
+            vec![0],
+        );
+        assert_eq!(
+            graph.to_exprs().unwrap_err(),
+            GraphError::InvalidInput { node: 0, input: 0 }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_graph_serde() {
+        let (res, taped) = sample();
+        let graph = Graph::new(&[res.clone(), taped.clone()]);
+
+        let json = serde_json::to_string(&graph).unwrap();
+        let restored: Graph<f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, graph);
+
+        let bin = bincode::serialize(&graph).unwrap();
+        let restored: Graph<f64> = bincode::deserialize(&bin).unwrap();
+        assert_eq!(restored, graph);
+
+        let exprs = restored.to_exprs().unwrap();
+        assert_same(&exprs[0], &res);
+        assert_same(&exprs[1], &taped);
+    }
+
+    #[test]
+    fn test_renew_groups() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(2.0_f64);
+        // graph loaded from another process whose group id collides with `grp`
+        let mut graph = Graph::from_parts(
+            vec![
+                Node::Var {
+                    id: x.id().clone(),
+                    o: 3.0,
+                },
+                Node::Binary {
+                    op: BinaryKind::Mul,
+                    l: 0,
+                    r: 0,
+                    o: 9.0,
+                    gl: 3.0,
+                    gr: 3.0,
+                },
+            ],
+            vec![1],
+        );
+        let groups = graph.renew_groups();
+        let loaded = &groups[&grp.id()];
+        assert_ne!(loaded.id(), grp.id());
+        assert_eq!(loaded.len(), 1);
+
+        let e = graph.to_exprs().unwrap().pop().unwrap() * x.clone().into_expr();
+        let grads = e.grads();
+        assert_eq!(grads.len(), 2);
+        assert_eq!(grads[x.id()], 9.0);
+        let y = Id {
+            group: loaded.id(),
+            name: "x".into(),
+            num: 0,
+        };
+        assert_eq!(grads[&y], 12.0);
+        assert_eq!(loaded.val(1.0).id().num, 1);
+    }
+}
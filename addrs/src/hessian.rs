@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    scalar::{Dual, Scalar},
+    Expr, Id, Var,
+};
+
+/// Calculates second order derivatives with forward-over-reverse mode.
+///
+/// `f` is evaluated with `Expr<Dual<T>>` once for each variable in `vars`,
+/// seeding the tangent of the variable with one.
+/// The tangents of adjoints obtained by the reverse sweep then give one column of the hessian.
+///
+/// The result is keyed by `(row, col)` and contains rows for every variable on which `f` depends,
+/// while columns are restricted to `vars`.
+pub fn hessian<T, F>(vars: &[Var<T>], f: F) -> BTreeMap<(Id, Id), T>
+where
+    T: Scalar,
+    F: Fn(&[Expr<Dual<T>>]) -> Expr<Dual<T>>,
+{
+    let mut res = BTreeMap::new();
+    for (j, col) in vars.iter().enumerate() {
+        let xs: Vec<_> = vars
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let tan = if i == j { T::one() } else { T::zero() };
+                let val = Dual::new(v.val().clone(), tan);
+                Var::from_parts(v.id().clone(), val).into_expr()
+            })
+            .collect();
+        for (row, g) in f(&xs).grads() {
+            let (_, h) = g.into_parts();
+            res.insert((row, col.id().clone()), h);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{Exp, Log, Sqrt},
+        VarGroup,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_hessian() {
+        let grp = VarGroup::new("x");
+        let (x, y) = (1.3_f64, 0.7_f64);
+        let vars = [grp.val(x), grp.val(y)];
+        let (ix, iy) = (vars[0].id().clone(), vars[1].id().clone());
+        let assert_hessian = |h: BTreeMap<(Id, Id), f64>, expected: [[f64; 2]; 2]| {
+            let ids = [&ix, &iy];
+            for (i, row) in expected.iter().enumerate() {
+                for (j, e) in row.iter().enumerate() {
+                    let actual = h
+                        .get(&(ids[i].clone(), ids[j].clone()))
+                        .cloned()
+                        .unwrap_or(0.0);
+                    assert!((actual - e).abs() < 1e-12, "{:?}", (i, j, actual, e));
+                }
+            }
+        };
+
+        let h = hessian(&vars, |v| v[0].clone().sqrt() * &v[1]);
+        let (hxx, hxy) = (-0.25 * y / x.powf(1.5), 0.5 / x.sqrt());
+        assert_hessian(h, [[hxx, hxy], [hxy, 0.0]]);
+
+        let h = hessian(&vars, |v| (v[0].clone() * &v[1]).exp());
+        let e = (x * y).exp();
+        let hxy = e * (1.0 + x * y);
+        assert_hessian(h, [[y * y * e, hxy], [hxy, x * x * e]]);
+
+        let h = hessian(&vars, |v| v[0].clone().log() + v[1].clone().log());
+        assert_hessian(h, [[-1.0 / (x * x), 0.0], [0.0, -1.0 / (y * y)]]);
+
+        let h = hessian(&vars, |v| v[0].clone() / &v[1]);
+        let hxy = -1.0 / (y * y);
+        assert_hessian(h, [[0.0, hxy], [hxy, 2.0 * x / (y * y * y)]]);
+    }
+}
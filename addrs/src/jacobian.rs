@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    private::{_Expr, _Row},
+    scalar::Scalar,
+    Expr, Id,
+};
+
+/// Sparse jacobian of several outputs.
+///
+/// Rows correspond to outputs and columns correspond to variables sorted by [`Id`].
+/// Entries are stored column by column.
+#[derive(Debug, Clone)]
+pub struct Jacobian<T> {
+    nrows: usize,
+    ids: Vec<Id>,
+    cols: Vec<Vec<(usize, T)>>,
+}
+
+impl<T> Jacobian<T> {
+    #[inline]
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+    #[inline]
+    pub fn ncols(&self) -> usize {
+        self.ids.len()
+    }
+    /// Variables corresponding to columns.
+    #[inline]
+    pub fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+    /// Returns a derivative of the `row`-th output by the variable `id`.
+    /// `None` means that the output does not depend on the variable.
+    pub fn get(&self, row: usize, id: &Id) -> Option<&T> {
+        let col = self.ids.binary_search(id).ok()?;
+        let col = &self.cols[col];
+        let i = col.binary_search_by_key(&row, |(i, _)| *i).ok()?;
+        Some(&col[i].1)
+    }
+    /// Returns non-zero entries as `(row, col, value)`.
+    pub fn triplets(&self) -> impl Iterator<Item = (usize, usize, &T)> {
+        self.cols
+            .iter()
+            .enumerate()
+            .flat_map(|(j, col)| col.iter().map(move |(i, x)| (*i, j, x)))
+    }
+}
+
+impl<T: Scalar> Jacobian<T> {
+    pub(crate) fn new(outputs: &[Expr<T>]) -> Self {
+        let roots = outputs
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e._ref(), _Row(vec![(i, T::one())])));
+        let mut res = BTreeMap::new();
+        _Expr::backward(roots, &mut res);
+        let (ids, cols) = res.into_iter().map(|(id, row)| (id, row.0)).unzip();
+        Self {
+            nrows: outputs.len(),
+            ids,
+            cols,
+        }
+    }
+    /// Returns non-zero entries as `(row, col, value)`.
+    pub fn to_triplets(&self) -> Vec<(usize, usize, T)> {
+        self.triplets().map(|(i, j, x)| (i, j, x.clone())).collect()
+    }
+    /// Returns a row-major dense matrix.
+    pub fn to_dense(&self) -> Vec<Vec<T>> {
+        let mut res = vec![vec![T::zero(); self.ncols()]; self.nrows];
+        for (i, j, x) in self.triplets() {
+            res[i][j] = x.clone();
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scalar::Exp, Tape, VarGroup};
+
+    use super::*;
+
+    #[test]
+    fn test_jacobian() {
+        let grp = VarGroup::new("x");
+        let r = grp.val(0.03_f64).into_expr();
+        let s = grp.val(0.01_f64).into_expr();
+        let tape = Tape::new();
+        let k = tape.var(grp.val(1.2));
+
+        let df = (-(r.clone() + &s) * 2.0).exp();
+        let outputs = vec![
+            df.clone() * 100.0,
+            df.clone() * &r,
+            -Expr::constant(3.0),
+            df.clone() * &k + &s,
+        ];
+        let jac = Expr::jacobian(&outputs);
+
+        assert_eq!(jac.nrows(), 4);
+        assert_eq!(jac.ncols(), 3);
+        for (i, output) in outputs.iter().enumerate() {
+            let grads = output.grads();
+            for id in jac.ids() {
+                let actual = jac.get(i, id).cloned();
+                assert_eq!(actual.is_some(), grads.contains(id));
+                if let Some(actual) = actual {
+                    assert!((actual - grads[id]).abs() < 1e-12);
+                }
+            }
+        }
+
+        let dense = jac.to_dense();
+        let triplets = jac.to_triplets();
+        // the third output is constant
+        assert_eq!(triplets.len(), 7);
+        for (i, j, x) in triplets {
+            assert_eq!(dense[i][j], x);
+        }
+        assert!(dense[2].iter().all(|x| *x == 0.0));
+    }
+}
@@ -1,10 +1,41 @@
+mod accumulator;
+mod check;
+mod checkpoint;
+mod custom;
+mod display;
+mod dot;
 mod expr;
+mod gradients;
+pub mod graph;
+mod hessian;
+mod jacobian;
+mod optimize;
 mod private;
-mod scalar;
+mod program;
+mod replay;
+pub mod scalar;
+mod select;
+mod tape;
 mod var;
 
+pub use accumulator::{Accumulator, CompressPolicy};
+pub use check::{
+    check_gradients, check_gradients_with, CheckOptions, FiniteDiff, GradientError, GradientReport,
+};
+pub use checkpoint::Checkpoint;
+pub use custom::{BinaryOp, CustomBinary, CustomUnary, UnaryOp};
+pub use dot::DotOptions;
 pub use expr::Expr;
-pub use var::{Var, VarGroup};
+pub use gradients::{AsId, Gradients};
+pub use graph::Graph;
+pub use hessian::hessian;
+pub use jacobian::Jacobian;
+pub use private::MaybeSync;
+pub use program::Program;
+pub use replay::Replay;
+pub use select::Cond;
+pub use tape::Tape;
+pub use var::{Id, Var, VarGroup};
 
 #[cfg(test)]
 mod tests {
@@ -39,4 +70,35 @@ mod tests {
         println!("{:?}", stopwatch.elapsed());
         println!("{:?}", grads.len());
     }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<S: Send + Sync>() {}
+        assert_send_sync::<Expr<f64>>();
+        assert_send_sync::<Var<f64>>();
+        assert_send_sync::<VarGroup>();
+        assert_send_sync::<Tape<f64>>();
+
+        let grp = VarGroup::new("x");
+        let x = grp.val(2.0).into_expr();
+        let trades: Vec<Expr<f64>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (1..=4)
+                .map(|k| {
+                    let (grp, x) = (grp.clone(), x.clone());
+                    s.spawn(move || {
+                        let mut pv = x * grp.val(k as f64).into_expr();
+                        pv.compress();
+                        pv
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let total = trades
+            .into_iter()
+            .fold(Expr::<f64>::zero(), |acc, pv| acc + pv);
+        assert_eq!(total.output(), &20.0);
+        assert_eq!(total.grads().len(), 5);
+    }
 }
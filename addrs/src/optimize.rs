@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use crate::{
+    graph::{BinaryKind, CmpKind, Node, UnaryKind},
+    scalar::{Scalar, Subgradient},
+    Expr, Graph,
+};
+
+/// Operand of a node used as a key of common-subexpression elimination.
+///
+/// Constants are compared by values after looking up the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum _Operand {
+    Node(usize),
+    Const,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum _Key {
+    Unary(UnaryKind, usize),
+    Binary(BinaryKind, _Operand, _Operand),
+    Select(CmpKind, [_Operand; 4]),
+}
+
+#[inline]
+fn _commutative(op: BinaryKind) -> bool {
+    // one-sided policies give different partials to operands at a tie
+    let symmetric = |p| matches!(p, Subgradient::Average | Subgradient::Error);
+    match op {
+        BinaryKind::Add | BinaryKind::Mul => true,
+        BinaryKind::Max(p) | BinaryKind::Min(p) => symmetric(p),
+        _ => false,
+    }
+}
+
+struct _Optimizer<T> {
+    nodes: Vec<Node<T>>,
+    keys: HashMap<_Key, Vec<usize>>,
+}
+
+impl<T: Scalar + PartialEq> _Optimizer<T> {
+    #[inline]
+    fn push(&mut self, node: Node<T>) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+    #[inline]
+    fn constant(&self, i: usize) -> Option<&T> {
+        match &self.nodes[i] {
+            Node::Const { o } => Some(o),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn operand(&self, i: usize) -> _Operand {
+        match self.constant(i) {
+            Some(_) => _Operand::Const,
+            None => _Operand::Node(i),
+        }
+    }
+    /// Whether two nodes are the same or constants with the same value.
+    #[inline]
+    fn same(&self, i: usize, j: usize) -> bool {
+        i == j || matches!((self.constant(i), self.constant(j)), (Some(x), Some(y)) if x == y)
+    }
+
+    fn unary(&mut self, node: Node<T>) -> usize {
+        let Node::Unary { op, i, .. } = &node else {
+            unreachable!()
+        };
+        let key = _Key::Unary(*op, *i);
+        if let Some(found) = self.keys.get(&key).and_then(|c| c.first()) {
+            return *found;
+        }
+        let idx = self.push(node);
+        self.keys.entry(key).or_default().push(idx);
+        idx
+    }
+
+    fn binary(&mut self, node: Node<T>) -> usize {
+        let Node::Binary { op, l, r, .. } = &node else {
+            unreachable!()
+        };
+        let (op, l, r) = (*op, *l, *r);
+        let (mut a, mut b) = (self.operand(l), self.operand(r));
+        if _commutative(op) && b < a {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let key = _Key::Binary(op, a, b);
+        for c in self.keys.get(&key).into_iter().flatten() {
+            let Node::Binary { l: cl, r: cr, .. } = &self.nodes[*c] else {
+                unreachable!()
+            };
+            if (self.same(l, *cl) && self.same(r, *cr))
+                || (_commutative(op) && self.same(l, *cr) && self.same(r, *cl))
+            {
+                return *c;
+            }
+        }
+        let idx = self.push(node);
+        self.keys.entry(key).or_default().push(idx);
+        idx
+    }
+
+    fn select(&mut self, node: Node<T>) -> usize {
+        let Node::Select {
+            cmp, x, y, a, b, ..
+        } = &node
+        else {
+            unreachable!()
+        };
+        let inputs = [*x, *y, *a, *b];
+        let key = _Key::Select(*cmp, inputs.map(|i| self.operand(i)));
+        for c in self.keys.get(&key).into_iter().flatten() {
+            if self.nodes[*c]
+                .inputs()
+                .iter()
+                .zip(inputs)
+                .all(|(i, j)| self.same(*i, j))
+            {
+                return *c;
+            }
+        }
+        let idx = self.push(node);
+        self.keys.entry(key).or_default().push(idx);
+        idx
+    }
+
+    /// Returns the other operand if `i` is a binary node of `op` with one constant operand.
+    #[inline]
+    fn with_constant(&self, i: usize, op: BinaryKind) -> Option<(usize, &T)> {
+        match &self.nodes[i] {
+            Node::Binary { op: o, l, r, .. } if *o == op => {
+                match (self.constant(*l), self.constant(*r)) {
+                    (None, Some(c)) => Some((*l, c)),
+                    (Some(c), None) => Some((*r, c)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn visit(&mut self, node: &Node<T>, remap: &[usize]) -> usize {
+        match node {
+            Node::Var { .. } | Node::Const { .. } => self.push(node.clone()),
+            Node::Compressed { o, gs, .. } if gs.is_empty() => {
+                self.push(Node::Const { o: o.clone() })
+            }
+            Node::Compressed { .. } => self.push(node.clone()),
+            Node::Unary { op, i, o, g } => {
+                let i = remap[*i];
+                if self.constant(i).is_some() {
+                    return self.push(Node::Const { o: o.clone() });
+                }
+                if let (
+                    UnaryKind::Neg,
+                    Node::Unary {
+                        op: UnaryKind::Neg,
+                        i,
+                        ..
+                    },
+                ) = (op, &self.nodes[i])
+                {
+                    return *i;
+                }
+                self.unary(Node::Unary {
+                    op: *op,
+                    i,
+                    o: o.clone(),
+                    g: g.clone(),
+                })
+            }
+            Node::Binary {
+                op,
+                l,
+                r,
+                o,
+                gl,
+                gr,
+            } => {
+                let (l, r) = (remap[*l], remap[*r]);
+                let (cl, cr) = (self.constant(l), self.constant(r));
+                if cl.is_some() && cr.is_some() {
+                    return self.push(Node::Const { o: o.clone() });
+                }
+                // identities
+                match (op, cl, cr) {
+                    (BinaryKind::Add, Some(c), _) if c.is_zero() => return r,
+                    (BinaryKind::Add | BinaryKind::Sub, _, Some(c)) if c.is_zero() => return l,
+                    (BinaryKind::Mul, Some(c), _) if c.is_one() => return r,
+                    (BinaryKind::Mul | BinaryKind::Div, _, Some(c)) if c.is_one() => return l,
+                    _ => {}
+                }
+                // chains of additions or multiplications by constants
+                if matches!(op, BinaryKind::Add | BinaryKind::Mul) {
+                    let chain = match (cl, cr) {
+                        (Some(c), None) => self.with_constant(r, *op).map(|x| (x, c)),
+                        (None, Some(c)) => self.with_constant(l, *op).map(|x| (x, c)),
+                        _ => None,
+                    };
+                    if let Some(((x, c1), c2)) = chain {
+                        let c = match op {
+                            BinaryKind::Add => c1.clone() + c2,
+                            _ => c1.clone() * c2,
+                        };
+                        let (gl, gr) = match op {
+                            BinaryKind::Add => (T::one(), T::one()),
+                            _ => (c.clone(), self.nodes[x].output().clone()),
+                        };
+                        let r = self.push(Node::Const { o: c });
+                        return self.binary(Node::Binary {
+                            op: *op,
+                            l: x,
+                            r,
+                            o: o.clone(),
+                            gl,
+                            gr,
+                        });
+                    }
+                }
+                self.binary(Node::Binary {
+                    op: *op,
+                    l,
+                    r,
+                    o: o.clone(),
+                    gl: gl.clone(),
+                    gr: gr.clone(),
+                })
+            }
+            // user-defined operations are only folded
+            Node::CustomUnary { op, i, o, g } => {
+                let i = remap[*i];
+                if self.constant(i).is_some() {
+                    return self.push(Node::Const { o: o.clone() });
+                }
+                self.push(Node::CustomUnary {
+                    op: op.clone(),
+                    i,
+                    o: o.clone(),
+                    g: g.clone(),
+                })
+            }
+            Node::CustomBinary {
+                op,
+                l,
+                r,
+                o,
+                gl,
+                gr,
+            } => {
+                let (l, r) = (remap[*l], remap[*r]);
+                if self.constant(l).is_some() && self.constant(r).is_some() {
+                    return self.push(Node::Const { o: o.clone() });
+                }
+                self.push(Node::CustomBinary {
+                    op: op.clone(),
+                    l,
+                    r,
+                    o: o.clone(),
+                    gl: gl.clone(),
+                    gr: gr.clone(),
+                })
+            }
+            Node::Select {
+                cmp,
+                x,
+                y,
+                a,
+                b,
+                o,
+                cond,
+            } => {
+                let [x, y, a, b] = [x, y, a, b].map(|i| remap[*i]);
+                // branches which never change or do not differ
+                if self.constant(x).is_some() && self.constant(y).is_some() {
+                    return if *cond { a } else { b };
+                }
+                if self.same(a, b) {
+                    return a;
+                }
+                self.select(Node::Select {
+                    cmp: *cmp,
+                    x,
+                    y,
+                    a,
+                    b,
+                    o: o.clone(),
+                    cond: *cond,
+                })
+            }
+        }
+    }
+}
+
+impl<T: Scalar + PartialEq> Graph<T> {
+    /// Returns an equivalent graph with fewer nodes.
+    ///
+    /// Structurally identical nodes are merged, constant subgraphs are folded,
+    /// trivial operations like `x * 1` and `-(-x)` are removed,
+    /// chains of additions or multiplications by constants are combined,
+    /// and nodes on which no output depends are pruned.
+    pub fn optimize(&self) -> Self {
+        let mut this = _Optimizer {
+            nodes: Vec::with_capacity(self.nodes().len()),
+            keys: HashMap::new(),
+        };
+        let mut remap = Vec::with_capacity(self.nodes().len());
+        for node in self.nodes() {
+            let idx = this.visit(node, &remap);
+            remap.push(idx);
+        }
+        let outputs: Vec<_> = self.outputs().iter().map(|i| remap[*i]).collect();
+
+        // pruning
+        let mut alive = vec![false; this.nodes.len()];
+        for i in &outputs {
+            alive[*i] = true;
+        }
+        for i in (0..this.nodes.len()).rev() {
+            if alive[i] {
+                for j in this.nodes[i].inputs() {
+                    alive[j] = true;
+                }
+            }
+        }
+        let mut remap = vec![usize::MAX; this.nodes.len()];
+        let mut nodes = Vec::new();
+        for (i, mut node) in this.nodes.into_iter().enumerate() {
+            if !alive[i] {
+                continue;
+            }
+            match &mut node {
+                Node::Unary { i, .. } | Node::CustomUnary { i, .. } => *i = remap[*i],
+                Node::Binary { l, r, .. } | Node::CustomBinary { l, r, .. } => {
+                    (*l, *r) = (remap[*l], remap[*r])
+                }
+                Node::Select { x, y, a, b, .. } => {
+                    for i in [x, y, a, b] {
+                        *i = remap[*i];
+                    }
+                }
+                _ => {}
+            }
+            remap[i] = nodes.len();
+            nodes.push(node);
+        }
+        let outputs = outputs.into_iter().map(|i| remap[i]).collect();
+        Self::from_parts(nodes, outputs)
+    }
+}
+
+impl<T: Scalar + PartialEq> Expr<T> {
+    /// Returns an equivalent expression with a smaller graph.
+    /// See [`Graph::optimize`] for details.
+    pub fn optimize(&self) -> Self {
+        let graph = Graph::from(self).optimize();
+        graph.to_exprs().unwrap().pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{Exp, Max, Min},
+        Replay, VarGroup,
+    };
+
+    use super::*;
+
+    fn assert_same(actual: &Expr<f64>, expected: &Expr<f64>) {
+        assert_eq!(actual.output(), expected.output());
+        let (actual, expected) = (actual.grads(), expected.grads());
+        assert_eq!(actual.len(), expected.len());
+        for (id, g) in expected {
+            assert!((actual[&id] - g).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_optimize() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(4.2).into_expr();
+        let y = grp.val(2.5).into_expr();
+
+        let z = x.clone() * y.clone() + y.clone() * x.clone() + (x.clone() * y.clone()).exp();
+        let w = -(-((x.clone() + 1.0) + 2.0)) * 1.0 + (y.clone() * 2.0) * 3.0;
+        let e = z * &w - w.clone() * 2.0 * 3.0;
+
+        let graph = Graph::from(&e);
+        let optimized = graph.optimize();
+        assert!(optimized.nodes().len() < graph.nodes().len());
+        let muls = optimized.nodes().iter().filter(|n| match n {
+            Node::Binary { op, l, r, .. } => {
+                *op == BinaryKind::Mul
+                    && optimized.nodes()[*l].inputs().is_empty()
+                    && optimized.nodes()[*r].inputs().is_empty()
+            }
+            _ => false,
+        });
+        // `x * y` and `y * 6`
+        assert_eq!(muls.count(), 2);
+        assert!(!optimized.nodes().iter().any(|n| matches!(
+            n,
+            Node::Unary {
+                op: UnaryKind::Neg,
+                ..
+            }
+        )));
+
+        assert_same(&e.optimize(), &e);
+    }
+
+    #[test]
+    fn test_optimize_constant() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(1.5);
+        let c = 0.5_f64.exp();
+        // compressed expression without derivatives is a constant
+        let graph = Graph::from_parts(
+            vec![
+                Node::Compressed {
+                    g: 0,
+                    o: 0.5,
+                    gs: vec![],
+                },
+                Node::Unary {
+                    op: UnaryKind::Exp,
+                    i: 0,
+                    o: c,
+                    g: c,
+                },
+                Node::Const { o: 1.0 },
+                Node::Binary {
+                    op: BinaryKind::Add,
+                    l: 1,
+                    r: 2,
+                    o: c + 1.0,
+                    gl: 1.0,
+                    gr: 1.0,
+                },
+                Node::Var {
+                    id: x.id().clone(),
+                    o: 1.5,
+                },
+                Node::Binary {
+                    op: BinaryKind::Mul,
+                    l: 3,
+                    r: 4,
+                    o: (c + 1.0) * 1.5,
+                    gl: 1.5,
+                    gr: c + 1.0,
+                },
+            ],
+            vec![5],
+        );
+        let optimized = graph.optimize();
+        assert_eq!(optimized.nodes().len(), 3);
+        assert_same(
+            &optimized.to_exprs().unwrap()[0],
+            &graph.to_exprs().unwrap()[0],
+        );
+    }
+
+    #[test]
+    fn test_optimize_max_min() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(1.0).into_expr();
+        // chains of max and min are not folded as products
+        let es = [x.clone().max(2.0).max(3.0), x.clone().min(5.0).min(3.0)];
+        let optimized: Vec<_> = es.iter().map(|e| e.optimize()).collect();
+        for (e, o) in es.iter().zip(&optimized) {
+            assert_same(o, e);
+        }
+
+        let id = x.as_var().unwrap().id();
+        let mut expected = Replay::new(&es);
+        let mut actual = Replay::new(&optimized);
+        for v in [0.0, 2.5, 4.0] {
+            assert!(expected.set(id, v) && actual.set(id, v));
+            expected.forward();
+            actual.forward();
+            for k in 0..2 {
+                assert_eq!(actual.output(k), expected.output(k));
+                assert_eq!(actual.grads(k)[&x], expected.grads(k)[&x]);
+            }
+        }
+
+        // operands swapped at a tie are not merged under one-sided policies
+        let y = grp.val(1.0).into_expr();
+        for p in [Subgradient::Left, Subgradient::Right, Subgradient::Average] {
+            let max = |a: &Expr<f64>, b: &Expr<f64>| a.clone().max_with(b, p).unwrap();
+            let min = |a: &Expr<f64>, b: &Expr<f64>| a.clone().min_with(b, p).unwrap();
+            let e = max(&x, &y) + max(&y, &x) * 10.0 + min(&x, &y) * 100.0 + min(&y, &x);
+            assert_same(&e.optimize(), &e);
+        }
+    }
+}
@@ -1,7 +1,20 @@
+mod _adjoint;
 mod _binary;
 mod _expr;
+mod _graph;
 mod _leaf;
 mod _node;
+mod _program;
+mod _select;
+mod _sync;
+mod _tape;
 mod _unary;
 
+pub(crate) use _adjoint::{_Adjoint, _Dense, _Row, _Sink};
 pub(crate) use _expr::_Expr;
+pub(crate) use _graph::_Graph;
+pub(crate) use _program::_Inst;
+pub(crate) use _select::_Select;
+pub(crate) use _sync::_Rc;
+pub use _sync::MaybeSync;
+pub(crate) use _tape::{_Tape, _TapeRef};
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use crate::{scalar::Scalar, var::Id};
+
+/// Adjoint propagated by the reverse sweep.
+///
+/// Usually this is a scalar, but a reverse sweep for several outputs at once
+/// propagates a vector of adjoints, one for each output.
+pub(crate) trait _Adjoint<T>: Clone {
+    /// Multiplies a local partial derivative.
+    fn scaled(self, g: &T) -> Self;
+    /// Adds an adjoint coming through another path.
+    fn merge(&mut self, other: Self);
+}
+
+impl<T: Scalar> _Adjoint<T> for T {
+    #[inline]
+    fn scaled(self, g: &T) -> Self {
+        self * g
+    }
+    #[inline]
+    fn merge(&mut self, other: Self) {
+        *self += &other;
+    }
+}
+
+/// Sparse vector of adjoints keyed by indices of outputs.
+///
+/// Entries are sorted by the index.
+#[derive(Debug, Clone)]
+pub(crate) struct _Row<T>(pub Vec<(usize, T)>);
+
+impl<T: Scalar> _Adjoint<T> for _Row<T> {
+    #[inline]
+    fn scaled(mut self, g: &T) -> Self {
+        for (_, x) in self.0.iter_mut() {
+            *x *= g;
+        }
+        self
+    }
+    fn merge(&mut self, other: Self) {
+        let lhs = std::mem::take(&mut self.0);
+        let mut merged = Vec::with_capacity(lhs.len().max(other.0.len()));
+        let mut lhs = lhs.into_iter().peekable();
+        let mut rhs = other.0.into_iter().peekable();
+        loop {
+            let next = match (lhs.peek(), rhs.peek()) {
+                (Some((i, _)), Some((j, _))) if i < j => lhs.next(),
+                (Some((i, _)), Some((j, _))) if i > j => rhs.next(),
+                (Some(_), Some(_)) => {
+                    let (i, mut x) = lhs.next().unwrap();
+                    x += &rhs.next().unwrap().1;
+                    Some((i, x))
+                }
+                (Some(_), None) => lhs.next(),
+                (None, Some(_)) => rhs.next(),
+                (None, None) => break,
+            };
+            merged.extend(next);
+        }
+        self.0 = merged;
+    }
+}
+
+/// Destination of adjoints of variables.
+pub(crate) trait _Sink<T, A> {
+    fn add(&mut self, id: &Id, grad: A);
+}
+
+impl<T, A: _Adjoint<T>> _Sink<T, A> for BTreeMap<Id, A> {
+    #[inline]
+    fn add(&mut self, id: &Id, grad: A) {
+        match self.get_mut(id) {
+            Some(x) => x.merge(grad),
+            None => {
+                self.insert(id.clone(), grad);
+            }
+        }
+    }
+}
+
+/// Dense buffer of adjoints of variables in a group, indexed by their numbers.
+/// Variables in other groups are ignored.
+pub(crate) struct _Dense<'a, T> {
+    pub group: usize,
+    pub buf: &'a mut [T],
+}
+
+impl<T: Scalar> _Sink<T, T> for _Dense<'_, T> {
+    #[inline]
+    fn add(&mut self, id: &Id, grad: T) {
+        if id.group == self.group {
+            self.buf[id.num] += &grad;
+        }
+    }
+}
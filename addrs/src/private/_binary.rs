@@ -1,104 +1,158 @@
 mod add;
+mod atan2;
+mod custom;
 mod div;
+mod max;
+mod min;
 mod mul;
+mod pow;
 mod sub;
 
-use std::{
-    collections::VecDeque,
-    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
-    rc::Rc,
-};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
-use crate::{scalar::Scalar, Expr};
+use crate::{
+    custom::CustomBinary,
+    scalar::{Elementary, Scalar, Subgradient},
+    Expr,
+};
 
-use super::{_expr::_Expr, _node::_Node};
+use super::{_adjoint::_Adjoint, _expr::_Expr, _node::_Node, _sync::_Rc, _tape::_Tape};
 
 #[derive(Debug, Clone)]
-enum _BOp {
+pub(crate) enum _BOp<T> {
     Add,
     Sub,
     Mul,
     Div,
+    Pow,
+    Atan2,
+    Max(Subgradient),
+    Min(Subgradient),
+    Custom(CustomBinary<T>),
+}
+
+impl<T: Elementary> _BOp<T> {
+    /// Returns the output and the partial derivatives at `(l, r)`.
+    #[inline]
+    pub fn eval(&self, l: &T, r: &T) -> (T, T, T) {
+        match self {
+            _BOp::Add => add::eval(l, r),
+            _BOp::Sub => sub::eval(l, r),
+            _BOp::Mul => mul::eval(l, r),
+            _BOp::Div => div::eval(l, r),
+            _BOp::Pow => pow::eval(l, r),
+            _BOp::Atan2 => atan2::eval(l, r),
+            _BOp::Max(p) => max::eval(l, r, *p),
+            _BOp::Min(p) => min::eval(l, r, *p),
+            _BOp::Custom(c) => c.eval(l, r),
+        }
+    }
 }
 
+/// Inputs of a binary operation.
+///
+/// Non-constant inputs are referred by `I` and constant inputs are kept as values.
 #[derive(Debug, Clone)]
-enum _In<T> {
-    L(Rc<_Expr<T>>),
-    R(Rc<_Expr<T>>),
-    LR { l: Rc<_Expr<T>>, r: Rc<_Expr<T>> },
+pub(crate) enum _In<I, C> {
+    L { l: I, r: C },
+    R { l: C, r: I },
+    LR { l: I, r: I },
+}
+
+impl<I, C> _In<I, C> {
+    #[inline]
+    pub fn backprop<'a, T, A: _Adjoint<T>>(
+        &'a self,
+        grad: A,
+        gl: &T,
+        gr: &T,
+        mut f: impl FnMut(&'a I, A),
+    ) {
+        match self {
+            _In::L { l, .. } => f(l, grad.scaled(gl)),
+            _In::R { r, .. } => f(r, grad.scaled(gr)),
+            _In::LR { l, r } => {
+                f(l, grad.clone().scaled(gl));
+                f(r, grad.scaled(gr));
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct _Binary<T> {
-    i: _In<T>,
+    i: _In<_Rc<_Expr<T>>, T>,
     o: T,
     gl: T,
     gr: T,
-    #[allow(dead_code)]
-    op: _BOp, // debug purpose
+    op: _BOp<T>,
 }
 
-impl<T> _Binary<T> {
-    fn create(l: Expr<T>, r: Expr<T>, o: T, gl: T, gr: T, op: _BOp) -> Expr<T> {
+impl<T: Clone> _Binary<T> {
+    pub(super) fn create(l: Expr<T>, r: Expr<T>, o: T, gl: T, gr: T, op: _BOp<T>) -> Expr<T> {
         if l._is_const() && r._is_const() {
             return Expr::constant(o);
         }
+        if l._is_taped() || r._is_taped() {
+            return _Tape::record_binary(l._take(), r._take(), o, gl, gr, op).into();
+        }
         let (gen, i) = match (l._is_const(), r._is_const()) {
             (true, false) => {
-                let r = Rc::new(r._take());
-                (r.generation() + 1, _In::R(r))
+                let l = l.output().clone();
+                let r = _Rc::new(r._take());
+                (r.generation() + 1, _In::R { l, r })
             }
             (false, true) => {
-                let l = Rc::new(l._take());
-                (l.generation() + 1, _In::L(l))
+                let l = _Rc::new(l._take());
+                let r = r.output().clone();
+                (l.generation() + 1, _In::L { l, r })
             }
             _ => {
-                let l = Rc::new(l._take());
-                let r = Rc::new(r._take());
+                let l = _Rc::new(l._take());
+                let r = _Rc::new(r._take());
                 (l.generation().max(r.generation()) + 1, _In::LR { l, r })
             }
         };
         let b = _Binary { i, o, gl, gr, op };
         _Expr::Node(gen, _Node::Binary(b)).into()
     }
+}
 
+impl<T> _Binary<T> {
     #[inline]
     pub fn output(&self) -> &T {
         &self.o
     }
     #[inline]
+    pub fn inputs(&self) -> &_In<_Rc<_Expr<T>>, T> {
+        &self.i
+    }
+    #[inline]
+    pub fn partials(&self) -> (&T, &T) {
+        (&self.gl, &self.gr)
+    }
+    #[inline]
+    pub fn op(&self) -> &_BOp<T> {
+        &self.op
+    }
+    #[inline]
     pub fn _ref_expr_for_drop(&mut self) -> (Option<&mut _Expr<T>>, Option<&mut _Expr<T>>) {
         match &mut self.i {
-            _In::L(l) => (Rc::get_mut(l), None),
-            _In::R(r) => (None, Rc::get_mut(r)),
-            _In::LR { l, r } => (Rc::get_mut(l), Rc::get_mut(r)),
+            _In::L { l, .. } => (_Rc::get_mut(l), None),
+            _In::R { r, .. } => (None, _Rc::get_mut(r)),
+            _In::LR { l, r } => (_Rc::get_mut(l), _Rc::get_mut(r)),
         }
     }
 }
 
 impl<T: Scalar> _Binary<T> {
-    pub fn push_grads<'a>(&'a self, grads: &mut VecDeque<(&'a _Expr<T>, T)>, grad: T) {
-        match &self.i {
-            _In::L(l) => {
-                if !l.is_const() {
-                    grads.push_back((l, grad * &self.gl))
-                }
-            }
-            _In::R(r) => {
-                if !r.is_const() {
-                    grads.push_back((r, grad * &self.gr))
-                }
+    #[inline]
+    pub fn backprop<'a, A: _Adjoint<T>>(&'a self, grad: A, mut f: impl FnMut(&'a _Expr<T>, A)) {
+        self.i.backprop(grad, &self.gl, &self.gr, |e, g| {
+            if !e.is_const() {
+                f(e, g)
             }
-            _In::LR { l, r } => match (l.is_const(), r.is_const()) {
-                (true, false) => grads.push_back((r, grad * &self.gr)),
-                (false, true) => grads.push_back((l, grad * &self.gl)),
-                (false, false) => {
-                    grads.push_back((l, grad.clone() * &self.gl));
-                    grads.push_back((r, grad * &self.gr));
-                }
-                _ => {}
-            },
-        }
+        })
     }
 }
 
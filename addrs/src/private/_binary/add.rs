@@ -11,9 +11,12 @@ where
     type Output = Self;
     #[inline]
     fn add(self, rhs: Self) -> Self::Output {
-        let o = self.output().clone() + rhs.output();
-        let gl = T::one();
-        let gr = T::one();
+        let (o, gl, gr) = eval(self.output(), rhs.output());
         _Binary::create(self, rhs, o, gl, gr, _BOp::Add)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar>(l: &T, r: &T) -> (T, T, T) {
+    (l.clone() + r, T::one(), T::one())
+}
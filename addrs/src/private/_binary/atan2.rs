@@ -0,0 +1,43 @@
+use crate::{
+    scalar::{Atan2, Scalar},
+    Expr,
+};
+
+use super::{_BOp, _Binary};
+
+impl<T: Scalar + Atan2> Atan2 for Expr<T> {
+    #[inline]
+    fn atan2(self, x: Self) -> Self {
+        let (o, gl, gr) = eval(self.output(), x.output());
+        _Binary::create(self, x, o, gl, gr, _BOp::Atan2)
+    }
+}
+
+/// Derivatives at the origin are taken as zero.
+#[inline]
+pub(super) fn eval<T: Scalar + Atan2>(y: &T, x: &T) -> (T, T, T) {
+    let o = y.clone().atan2(x.clone());
+    let r2 = x.clone() * x + y.clone() * y;
+    if r2.is_zero() {
+        return (o, T::zero(), T::zero());
+    }
+    let gl = x.clone() / &r2;
+    let gr = -y.clone() / &r2;
+    (o, gl, gr)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::VarGroup;
+
+    use super::*;
+
+    #[test]
+    fn test_atan2_origin() {
+        let grp = VarGroup::new("x");
+        let y = grp.val(0.0_f64).into_expr();
+        let x = grp.val(0.0_f64).into_expr();
+        let grads = y.clone().atan2(x.clone()).grads();
+        assert_eq!((grads[&y], grads[&x]), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,12 @@
+use crate::{custom::CustomBinary, Expr};
+
+use super::{_BOp, _Binary};
+
+impl<T: Clone> CustomBinary<T> {
+    /// Applies the operation to `(l, r)` as a single node.
+    #[inline]
+    pub fn apply(&self, l: Expr<T>, r: Expr<T>) -> Expr<T> {
+        let (o, gl, gr) = self.eval(l.output(), r.output());
+        _Binary::create(l, r, o, gl, gr, _BOp::Custom(self.clone()))
+    }
+}
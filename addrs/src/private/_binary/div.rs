@@ -11,9 +11,15 @@ where
     type Output = Self;
     #[inline]
     fn div(self, rhs: Self) -> Self::Output {
-        let o = self.output().clone() / rhs.output();
-        let gl = T::one() / rhs.output();
-        let gr = -o.clone() / rhs.output();
+        let (o, gl, gr) = eval(self.output(), rhs.output());
         _Binary::create(self, rhs, o, gl, gr, _BOp::Div)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar>(l: &T, r: &T) -> (T, T, T) {
+    let o = l.clone() / r;
+    let gl = T::one() / r;
+    let gr = -o.clone() / r;
+    (o, gl, gr)
+}
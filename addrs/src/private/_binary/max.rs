@@ -0,0 +1,160 @@
+use crate::{
+    scalar::{_is_nan, KinkError, Max, Scalar, Subgradient},
+    Expr,
+};
+
+use super::{_BOp, _Binary};
+
+impl<T: Scalar + PartialOrd> Max for Expr<T> {
+    #[inline]
+    fn max_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(self.output(), rhs.output())?;
+        let (o, gl, gr) = eval(self.output(), rhs.output(), policy);
+        Ok(_Binary::create(self, rhs, o, gl, gr, _BOp::Max(policy)))
+    }
+}
+impl<T: Scalar + PartialOrd> Max<&Self> for Expr<T> {
+    #[inline]
+    fn max_with(self, rhs: &Self, policy: Subgradient) -> Result<Self, KinkError> {
+        self.max_with(rhs.clone(), policy)
+    }
+}
+impl<T: Scalar + PartialOrd> Max<T> for Expr<T> {
+    #[inline]
+    fn max_with(self, rhs: T, policy: Subgradient) -> Result<Self, KinkError> {
+        self.max_with(Expr::constant(rhs), policy)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + PartialOrd>(l: &T, r: &T, policy: Subgradient) -> (T, T, T) {
+    if l > r {
+        (l.clone(), T::one(), T::zero())
+    } else if l < r {
+        (r.clone(), T::zero(), T::one())
+    } else if _is_nan(l) || _is_nan(r) {
+        let nan = T::from(f64::NAN);
+        (nan.clone(), nan.clone(), nan)
+    } else {
+        // partials sum to one, which is the derivative along `l = r`
+        let gl = policy.pick(T::zero(), T::one());
+        let gr = policy.pick(T::one(), T::zero());
+        (l.clone(), gl, gr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::assert_gradients,
+        scalar::{Abs, Clamp, Dual, Min, Relu},
+        Replay, VarGroup,
+    };
+
+    use super::*;
+
+    fn f<S: Scalar + Abs + Relu + Max + Min>(x: &[S]) -> S {
+        let a = x[0].clone().max(x[1].clone()) * &x[2].clone().abs();
+        let b = (x[0].clone() - &x[2]).relu() + &x[1].clone().min(x[2].clone());
+        a + &(b * &x[1].clone().clamp(S::from(-1.0), S::from(0.5)))
+    }
+
+    #[test]
+    fn test_non_smooth() {
+        assert_gradients(&[0.3, -0.2, -0.7], f, f);
+    }
+
+    #[test]
+    fn test_subgradient() {
+        let grp = VarGroup::new("s");
+        let s = grp.val(100.0_f64).into_expr();
+        let payoff = |p| (s.clone() - 100.0).max_with(0.0, p);
+        for (p, g) in [
+            (Subgradient::Left, 0.0),
+            (Subgradient::Right, 1.0),
+            (Subgradient::Average, 0.5),
+        ] {
+            assert_eq!(payoff(p).unwrap().grads()[&s], g);
+            assert_eq!((s.clone() - 100.0).relu_with(p).unwrap().grads()[&s], g);
+        }
+        assert_eq!(payoff(Subgradient::Error).unwrap_err(), KinkError);
+        assert_eq!((s.clone() - 100.0).abs().grads()[&s], 0.0);
+
+        // kinks are checked again on replay
+        let e = (s.clone() - 90.0)
+            .max_with(0.0, Subgradient::Error)
+            .unwrap();
+        assert_eq!(e.grads()[&s], 1.0);
+        let mut replay = Replay::new(&[e]);
+        assert!(replay.set(s.as_var().unwrap().id(), 90.0));
+        replay.forward();
+        assert_eq!(*replay.output(0), 0.0);
+        assert!(replay.grads(0)[&s].is_nan());
+    }
+
+    #[test]
+    fn test_tie() {
+        let grp = VarGroup::new("x");
+        let s = grp.val(100.0_f64).into_expr();
+        let k = grp.val(100.0_f64).into_expr();
+        for (p, g) in [
+            (Subgradient::Left, 0.0),
+            (Subgradient::Right, 1.0),
+            (Subgradient::Average, 0.5),
+        ] {
+            // derivative along `l = r` is one whatever the policy
+            let e = s.clone().max_with(&s, p).unwrap();
+            assert_eq!(e.grads()[&s], 1.0);
+            let e = s.clone().min_with(&s, p).unwrap();
+            assert_eq!(e.grads()[&s], 1.0);
+            let d = Dual::new(100.0, 1.0);
+            assert_eq!(*Dual::tan(&d.max_with(d, p).unwrap()), 1.0);
+            assert_eq!(*Dual::tan(&d.min_with(d, p).unwrap()), 1.0);
+
+            let grads = (s.clone() - &k).max_with(0.0, p).unwrap().grads();
+            assert_eq!((grads[&s], grads[&k]), (g, -g));
+            let grads = (s.clone() - &k).min_with(0.0, p).unwrap().grads();
+            assert_eq!((grads[&s], grads[&k]), (1.0 - g, g - 1.0));
+            let (l, r) = (Dual::new(1.0, 2.0), Dual::new(1.0, 3.0));
+            let max = *Dual::tan(&l.max_with(r, p).unwrap());
+            assert_eq!(max, 2.0 * g + 3.0 * (1.0 - g));
+            let min = *Dual::tan(&l.min_with(r, p).unwrap());
+            assert_eq!(min, 2.0 * (1.0 - g) + 3.0 * g);
+        }
+    }
+
+    #[test]
+    fn test_nan() {
+        let grp = VarGroup::new("x");
+        let one = grp.val(1.0_f64).into_expr();
+        let nan = grp.val(f64::NAN).into_expr();
+        for (l, r) in [(&one, &nan), (&nan, &one)] {
+            let e = l.clone().max(r.clone());
+            assert!(e.output().is_nan() && e.grads()[&one].is_nan());
+            let e = l.clone().min(r.clone());
+            assert!(e.output().is_nan() && e.grads()[&one].is_nan());
+            let (l, r) = (*l.output(), *r.output());
+            assert!(Max::max(l, r).is_nan() && Min::min(l, r).is_nan());
+            let (l, r) = (Dual::new(l, 1.0), Dual::new(r, 1.0));
+            assert!(Dual::tan(&l.max(r)).is_nan() && Dual::tan(&l.min(r)).is_nan());
+        }
+        assert!(Relu::relu(f64::NAN).is_nan());
+        assert!(nan.clone().relu().grads()[&nan].is_nan());
+
+        // kinks and NaN fail in the same way for all scalars
+        let p = Subgradient::Error;
+        for (l, r) in [(1.0, 1.0), (1.0, f64::NAN)] {
+            assert_eq!(l.max_with(r, p), Err(KinkError));
+            assert_eq!(l.min_with(r, p), Err(KinkError));
+            assert_eq!(
+                Dual::new(l, 0.0).max_with(Dual::new(r, 0.0), p),
+                Err(KinkError)
+            );
+            let e = Expr::constant(l).max_with(r, p);
+            assert_eq!(e.unwrap_err(), KinkError);
+        }
+        assert_eq!(0.0_f64.abs_with(p), Err(KinkError));
+        assert_eq!(f64::NAN.relu_with(p), Err(KinkError));
+        assert_eq!(2.0_f64.max_with(1.0, p), Ok(2.0));
+    }
+}
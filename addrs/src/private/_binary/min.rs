@@ -0,0 +1,43 @@
+use crate::{
+    scalar::{_is_nan, KinkError, Min, Scalar, Subgradient},
+    Expr,
+};
+
+use super::{_BOp, _Binary};
+
+impl<T: Scalar + PartialOrd> Min for Expr<T> {
+    #[inline]
+    fn min_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(self.output(), rhs.output())?;
+        let (o, gl, gr) = eval(self.output(), rhs.output(), policy);
+        Ok(_Binary::create(self, rhs, o, gl, gr, _BOp::Min(policy)))
+    }
+}
+impl<T: Scalar + PartialOrd> Min<&Self> for Expr<T> {
+    #[inline]
+    fn min_with(self, rhs: &Self, policy: Subgradient) -> Result<Self, KinkError> {
+        self.min_with(rhs.clone(), policy)
+    }
+}
+impl<T: Scalar + PartialOrd> Min<T> for Expr<T> {
+    #[inline]
+    fn min_with(self, rhs: T, policy: Subgradient) -> Result<Self, KinkError> {
+        self.min_with(Expr::constant(rhs), policy)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + PartialOrd>(l: &T, r: &T, policy: Subgradient) -> (T, T, T) {
+    if l < r {
+        (l.clone(), T::one(), T::zero())
+    } else if l > r {
+        (r.clone(), T::zero(), T::one())
+    } else if _is_nan(l) || _is_nan(r) {
+        let nan = T::from(f64::NAN);
+        (nan.clone(), nan.clone(), nan)
+    } else {
+        let gl = policy.pick(T::one(), T::zero());
+        let gr = policy.pick(T::zero(), T::one());
+        (l.clone(), gl, gr)
+    }
+}
@@ -11,9 +11,12 @@ where
     type Output = Self;
     #[inline]
     fn mul(self, rhs: Self) -> Self::Output {
-        let o = self.output().clone() * rhs.output();
-        let gl = rhs.output().clone();
-        let gr = self.output().clone();
+        let (o, gl, gr) = eval(self.output(), rhs.output());
         _Binary::create(self, rhs, o, gl, gr, _BOp::Mul)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar>(l: &T, r: &T) -> (T, T, T) {
+    (l.clone() * r, r.clone(), l.clone())
+}
@@ -0,0 +1,106 @@
+use crate::{
+    scalar::{Log, Powf, Scalar},
+    Expr,
+};
+
+use super::{_BOp, _Binary};
+
+impl<T: Scalar + Powf + Log + PartialEq> Powf for Expr<T> {
+    #[inline]
+    fn powf(self, n: Self) -> Self {
+        let (o, gl, gr) = eval(self.output(), n.output());
+        _Binary::create(self, n, o, gl, gr, _BOp::Pow)
+    }
+}
+impl<T: Scalar + Powf + Log + PartialEq> Powf<&Self> for Expr<T> {
+    #[inline]
+    fn powf(self, n: &Self) -> Self {
+        self.powf(n.clone())
+    }
+}
+impl<T: Scalar + Powf + Log + PartialEq> Powf<T> for Expr<T> {
+    #[inline]
+    fn powf(self, n: T) -> Self {
+        self.powf(Expr::constant(n))
+    }
+}
+
+/// Derivatives are taken as zero where they vanish in the limit,
+/// i.e. by the base for zero exponents and by the exponent at zero base.
+///
+/// Zero base is tested by value, since a zero of [`crate::scalar::Dual`] may have a tangent.
+/// Its derivative by the exponent then keeps the tangent of `o * log(l)` without the logarithm,
+/// which agrees with that of the derivative by the base so that hessians are symmetric.
+#[inline]
+pub(super) fn eval<T: Scalar + Powf + Log + PartialEq>(l: &T, r: &T) -> (T, T, T) {
+    let o = l.clone().powf(r.clone());
+    let gl = match r.is_zero() {
+        true => T::zero(),
+        false => r.clone() * l.clone().powf(r.clone() - T::one()),
+    };
+    let gr = if l.is_zero() {
+        T::zero()
+    } else if *l == T::zero() {
+        l.clone() * l.clone().powf(r.clone() - T::one())
+    } else {
+        o.clone() * l.clone().log()
+    };
+    (o, gl, gr)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::assert_gradients,
+        hessian,
+        scalar::{Dual, Powi},
+        VarGroup,
+    };
+
+    use super::*;
+
+    fn f<S: Scalar + Powf + Powi>(x: &[S]) -> S {
+        let df = (x[0].clone() + &S::one()).powf(-x[1].clone());
+        df * &x[2].clone().powi(3) + &x[2].clone().powf(S::from(0.5)) - &x[0].clone().powi(-2)
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_gradients(&[0.03, 2.5, 1.2], f, f);
+
+        // edge cases at zero base
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.0_f64).into_expr();
+        let y = grp.val(1.0_f64).into_expr();
+        let grads = x.clone().powf(y.clone()).grads();
+        assert_eq!((grads[&x], grads[&y]), (1.0, 0.0));
+        let grads = x.clone().powf(0.0).grads();
+        assert_eq!(grads[&x], 0.0);
+        let grads = x.clone().powi(0).grads();
+        assert_eq!(grads[&x], 0.0);
+        let grads = x.clone().powi(2).grads();
+        assert_eq!(grads[&x], 0.0);
+        // discount factor at r = -1
+        let r = grp.val(-1.0_f64).into_expr();
+        let df = (r.clone() + 1.0).powf(2.0);
+        assert_eq!((*df.output(), df.grads()[&r]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pow_zero_base_second_order() {
+        // forward mode agrees with reverse mode
+        let d = Dual::new(0.0, 0.0).powf(Dual::new(1.0, 1.0));
+        assert_eq!((*d.val(), *Dual::tan(&d)), (0.0, 0.0));
+        let d = Dual::new(0.0, 1.0).powf(Dual::new(1.0, 0.0));
+        assert_eq!((*d.val(), *Dual::tan(&d)), (0.0, 1.0));
+
+        let grp = VarGroup::new("x");
+        let vars = [grp.val(0.0_f64), grp.val(1.0_f64)];
+        let h = hessian(&vars, |v| v[0].clone().powf(v[1].clone()));
+        let (x, y) = (vars[0].id().clone(), vars[1].id().clone());
+        assert_eq!(h[&(x.clone(), x.clone())], 0.0);
+        assert_eq!(h[&(x.clone(), y.clone())], 1.0);
+        assert_eq!(h[&(y.clone(), x.clone())], 1.0);
+        assert_eq!(h.get(&(y.clone(), y)).copied().unwrap_or(0.0), 0.0);
+    }
+}
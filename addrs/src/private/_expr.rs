@@ -1,10 +1,17 @@
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
 
 use derivative::Derivative;
 
 use crate::{scalar::Scalar, var::Id, Var};
 
-use super::{_leaf::_Leaf, _node::_Node};
+use super::{
+    _adjoint::{_Adjoint, _Sink},
+    _binary::_In,
+    _leaf::_Leaf,
+    _node::_Node,
+    _sync::_Rc,
+    _tape::_Taped,
+};
 
 #[derive(Clone, Derivative)]
 #[derivative(Debug = "transparent")]
@@ -12,7 +19,12 @@ pub(crate) enum _Expr<T> {
     _OnlyForDrop,
     Leaf(_Leaf<T>),
     Node(usize, _Node<T>),
-    Compressed { g: usize, o: T, gs: BTreeMap<Id, T> },
+    Compressed {
+        g: usize,
+        o: T,
+        gs: _Rc<BTreeMap<Id, T>>,
+    },
+    Taped(_Taped<T>),
 }
 
 impl<T> From<Var<T>> for _Expr<T> {
@@ -42,6 +54,7 @@ impl<T> _Expr<T> {
             Self::Leaf(l) => l.val(),
             Self::Node(_, n) => n.output(),
             Self::Compressed { o, .. } => o,
+            Self::Taped(t) => t.output(),
         }
     }
     #[inline]
@@ -51,24 +64,59 @@ impl<T> _Expr<T> {
             Self::Leaf(_) => 0,
             Self::Node(g, _) => *g,
             Self::Compressed { g, .. } => *g,
+            Self::Taped(t) => t.generation(),
+        }
+    }
+    /// Number of distinct nodes reachable without passing through compressed or taped ones.
+    ///
+    /// Nodes whose addresses are in `visited` are skipped and those of counted nodes are inserted,
+    /// so that nodes can be counted incrementally over several expressions.
+    /// `self` is always counted but not inserted, since it may be moved later.
+    /// Addresses of the others are valid as long as `self` is alive.
+    pub fn count_new_nodes(&self, visited: &mut HashSet<usize>) -> usize {
+        let len = visited.len();
+        let mut stack = vec![];
+        self.push_inputs(&mut stack);
+        while let Some(e) = stack.pop() {
+            if visited.insert(e as *const Self as usize) {
+                e.push_inputs(&mut stack);
+            }
+        }
+        1 + visited.len() - len
+    }
+    #[inline]
+    fn push_inputs<'a>(&'a self, stack: &mut Vec<&'a Self>) {
+        match self {
+            Self::Node(_, _Node::Unary(u)) => stack.push(u.input()),
+            Self::Node(_, _Node::Binary(b)) => match b.inputs() {
+                _In::L { l, .. } => stack.push(l),
+                _In::R { r, .. } => stack.push(r),
+                _In::LR { l, r } => stack.extend([l.as_ref(), r.as_ref()]),
+            },
+            Self::Node(_, _Node::Select(s)) => stack.extend(s.inputs()),
+            _ => {}
         }
     }
     #[inline]
     pub fn is_const(&self) -> bool {
         matches!(self, Self::Leaf(_Leaf::Const(_)))
     }
+    #[inline]
+    pub fn is_taped(&self) -> bool {
+        matches!(self, Self::Taped(_))
+    }
 }
 
 #[allow(dead_code)]
 impl<T: Scalar> _Expr<T> {
     #[inline]
     pub fn grads(&self, seed: T) -> BTreeMap<Id, T> {
-        self.grads_v1(seed)
+        self.grads_v2(seed)
     }
 
     fn grads_v1(&self, seed: T) -> BTreeMap<Id, T> {
         if let Self::Compressed { gs, .. } = self {
-            let mut res = gs.clone();
+            let mut res = (**gs).clone();
             for (_, g) in res.iter_mut() {
                 *g *= &seed;
             }
@@ -88,15 +136,128 @@ impl<T: Scalar> _Expr<T> {
                     }
                     _Leaf::Const(_) => {}
                 },
-                _Expr::Node(_, n) => n.push_grads(&mut grads, grad),
+                _Expr::Node(_, n) => n.backprop(grad, |e, g| grads.push_back((e, g))),
                 _Expr::Compressed { gs, .. } => {
-                    for (id, g) in gs {
+                    for (id, g) in gs.iter() {
                         let g = grad.clone() * g;
                         res.entry(id.clone()).and_modify(|x| *x += &g).or_insert(g);
                     }
                 }
+                _Expr::Taped(t) => _Taped::backprop(vec![(t, grad)], &mut res),
             }
         }
         res
     }
+
+    /// Reverse sweep in topological order.
+    fn grads_v2(&self, seed: T) -> BTreeMap<Id, T> {
+        let mut res = BTreeMap::new();
+        Self::backward([(self, seed)], &mut res);
+        res
+    }
+
+    /// Reverse sweep in topological order from several roots at once.
+    ///
+    /// Every input of a node has a strictly smaller generation than the node itself.
+    /// Hence, popping nodes from a max-heap keyed by generation guarantees that
+    /// the adjoint of a node is complete before it is propagated to its inputs,
+    /// and each shared node is visited only once.
+    ///
+    /// Adjoints of variables are accumulated into `res`.
+    pub fn backward<'a, A: _Adjoint<T>>(
+        roots: impl IntoIterator<Item = (&'a Self, A)>,
+        res: &mut impl _Sink<T, A>,
+    ) where
+        T: 'a,
+    {
+        let mut adjoints = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        let mut taped = Vec::new();
+        for (root, seed) in roots {
+            _accumulate(root, seed, res, &mut adjoints, &mut queue);
+        }
+        while let Some((_, key)) = queue.pop() {
+            let (node, grad) = adjoints.remove(&key).unwrap();
+            match node {
+                _Expr::Node(_, n) => n.backprop(grad, |e, g| {
+                    _accumulate(e, g, res, &mut adjoints, &mut queue)
+                }),
+                _Expr::Compressed { gs, .. } => {
+                    for (id, g) in gs.iter() {
+                        res.add(id, grad.clone().scaled(g));
+                    }
+                }
+                // nodes in a tape never refer to nodes outside of the tape.
+                // so all of them can be swept together at last.
+                _Expr::Taped(t) => taped.push((t, grad)),
+                _ => unreachable!(),
+            }
+        }
+        _Taped::backprop(taped, res);
+    }
+}
+
+/// Adds `grad` to the adjoint of `node`.
+///
+/// Variables are accumulated into the result directly.
+/// Other nodes are queued at their first visit and their adjoints are summed up
+/// until they are popped.
+#[inline]
+fn _accumulate<'a, T, A: _Adjoint<T>>(
+    node: &'a _Expr<T>,
+    grad: A,
+    res: &mut impl _Sink<T, A>,
+    adjoints: &mut HashMap<*const _Expr<T>, (&'a _Expr<T>, A)>,
+    queue: &mut BinaryHeap<(usize, *const _Expr<T>)>,
+) {
+    match node {
+        _Expr::_OnlyForDrop => unreachable!(),
+        _Expr::Leaf(_Leaf::Var(v)) => res.add(v.id(), grad),
+        _Expr::Leaf(_Leaf::Const(_)) => {}
+        _Expr::Node(..) | _Expr::Compressed { .. } | _Expr::Taped(_) => {
+            let key = node as *const _Expr<T>;
+            match adjoints.get_mut(&key) {
+                Some((_, x)) => x.merge(grad),
+                None => {
+                    adjoints.insert(key, (node, grad));
+                    queue.push((node.generation(), key));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scalar::Exp, Expr, VarGroup};
+
+    #[test]
+    fn test_grads_v2_matches_v1() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(0.3_f64).into_expr();
+        let y = grp.val(1.7).into_expr();
+        let df = (x.clone() * &y).exp();
+        let res = (df.clone() * &x + df.clone() / &y - &df * 2.0)._take();
+
+        let v1 = res.grads_v1(1.0);
+        let v2 = res.grads_v2(1.0);
+        assert_eq!(v1.len(), v2.len());
+        for (id, g) in v1 {
+            assert!((g - v2[&id]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_grads_v2_visits_shared_nodes_once() {
+        // the number of paths from the output to `x` is 2^200
+        let grp = VarGroup::new("x");
+        let x = grp.val(1.5).into_expr();
+        let mut y: Expr<f64> = x.clone();
+        for _ in 0..200 {
+            y = (y.clone() + &y) * 0.5;
+        }
+        let grads = y.grads();
+        assert_eq!(grads.len(), 1);
+        assert!((grads[x.as_var().unwrap().id()] - 1.0).abs() < 1e-12);
+    }
 }
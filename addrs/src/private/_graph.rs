@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+
+use crate::{
+    graph::{BinaryKind, GraphError, Node, UnaryKind},
+    scalar::Elementary,
+    var::Id,
+    Expr, Graph, Var,
+};
+
+use super::{
+    _binary::{_BOp, _Binary, _In},
+    _expr::_Expr,
+    _leaf::_Leaf,
+    _node::_Node,
+    _select::_Select,
+    _sync::_Rc,
+    _tape::{_Kind, _Taped},
+    _unary::{_UOp, _Unary},
+};
+
+impl<T> _UOp<T> {
+    /// Graph node of this operation.
+    fn node(&self, i: usize, o: T, g: T) -> Node<T> {
+        let op = match self {
+            _UOp::Neg => UnaryKind::Neg,
+            _UOp::Sqrt => UnaryKind::Sqrt,
+            _UOp::Exp => UnaryKind::Exp,
+            _UOp::Log => UnaryKind::Log,
+            _UOp::Powi(n) => UnaryKind::Powi(*n),
+            _UOp::Sin => UnaryKind::Sin,
+            _UOp::Cos => UnaryKind::Cos,
+            _UOp::Tan => UnaryKind::Tan,
+            _UOp::Asin => UnaryKind::Asin,
+            _UOp::Acos => UnaryKind::Acos,
+            _UOp::Atan => UnaryKind::Atan,
+            _UOp::Tanh => UnaryKind::Tanh,
+            _UOp::Sinh => UnaryKind::Sinh,
+            _UOp::Cosh => UnaryKind::Cosh,
+            _UOp::Asinh => UnaryKind::Asinh,
+            _UOp::Ln1p => UnaryKind::Ln1p,
+            _UOp::ExpM1 => UnaryKind::ExpM1,
+            _UOp::Log2 => UnaryKind::Log2,
+            _UOp::Log10 => UnaryKind::Log10,
+            _UOp::Exp2 => UnaryKind::Exp2,
+            _UOp::Cbrt => UnaryKind::Cbrt,
+            _UOp::Erf => UnaryKind::Erf,
+            _UOp::Erfc => UnaryKind::Erfc,
+            _UOp::NormPdf => UnaryKind::NormPdf,
+            _UOp::NormCdf => UnaryKind::NormCdf,
+            _UOp::NormInvCdf => UnaryKind::NormInvCdf,
+            _UOp::Abs(p) => UnaryKind::Abs(*p),
+            _UOp::Relu(p) => UnaryKind::Relu(*p),
+            _UOp::Custom(op) => {
+                let op = op.clone();
+                return Node::CustomUnary { op, i, o, g };
+            }
+        };
+        Node::Unary { op, i, o, g }
+    }
+}
+impl<T> From<UnaryKind> for _UOp<T> {
+    #[inline]
+    fn from(op: UnaryKind) -> Self {
+        match op {
+            UnaryKind::Neg => Self::Neg,
+            UnaryKind::Sqrt => Self::Sqrt,
+            UnaryKind::Exp => Self::Exp,
+            UnaryKind::Log => Self::Log,
+            UnaryKind::Powi(n) => Self::Powi(n),
+            UnaryKind::Sin => Self::Sin,
+            UnaryKind::Cos => Self::Cos,
+            UnaryKind::Tan => Self::Tan,
+            UnaryKind::Asin => Self::Asin,
+            UnaryKind::Acos => Self::Acos,
+            UnaryKind::Atan => Self::Atan,
+            UnaryKind::Tanh => Self::Tanh,
+            UnaryKind::Sinh => Self::Sinh,
+            UnaryKind::Cosh => Self::Cosh,
+            UnaryKind::Asinh => Self::Asinh,
+            UnaryKind::Ln1p => Self::Ln1p,
+            UnaryKind::ExpM1 => Self::ExpM1,
+            UnaryKind::Log2 => Self::Log2,
+            UnaryKind::Log10 => Self::Log10,
+            UnaryKind::Exp2 => Self::Exp2,
+            UnaryKind::Cbrt => Self::Cbrt,
+            UnaryKind::Erf => Self::Erf,
+            UnaryKind::Erfc => Self::Erfc,
+            UnaryKind::NormPdf => Self::NormPdf,
+            UnaryKind::NormCdf => Self::NormCdf,
+            UnaryKind::NormInvCdf => Self::NormInvCdf,
+            UnaryKind::Abs(p) => Self::Abs(p),
+            UnaryKind::Relu(p) => Self::Relu(p),
+        }
+    }
+}
+impl<T> _BOp<T> {
+    /// Graph node of this operation.
+    fn node(&self, l: usize, r: usize, o: T, gl: T, gr: T) -> Node<T> {
+        let op = match self {
+            _BOp::Add => BinaryKind::Add,
+            _BOp::Sub => BinaryKind::Sub,
+            _BOp::Mul => BinaryKind::Mul,
+            _BOp::Div => BinaryKind::Div,
+            _BOp::Pow => BinaryKind::Pow,
+            _BOp::Atan2 => BinaryKind::Atan2,
+            _BOp::Max(p) => BinaryKind::Max(*p),
+            _BOp::Min(p) => BinaryKind::Min(*p),
+            _BOp::Custom(op) => {
+                let op = op.clone();
+                return Node::CustomBinary {
+                    op,
+                    l,
+                    r,
+                    o,
+                    gl,
+                    gr,
+                };
+            }
+        };
+        Node::Binary {
+            op,
+            l,
+            r,
+            o,
+            gl,
+            gr,
+        }
+    }
+}
+impl<T> From<BinaryKind> for _BOp<T> {
+    #[inline]
+    fn from(op: BinaryKind) -> Self {
+        match op {
+            BinaryKind::Add => Self::Add,
+            BinaryKind::Sub => Self::Sub,
+            BinaryKind::Mul => Self::Mul,
+            BinaryKind::Div => Self::Div,
+            BinaryKind::Pow => Self::Pow,
+            BinaryKind::Atan2 => Self::Atan2,
+            BinaryKind::Max(p) => Self::Max(p),
+            BinaryKind::Min(p) => Self::Min(p),
+        }
+    }
+}
+
+impl UnaryKind {
+    /// Returns the output and the partial derivative at `x`.
+    #[inline]
+    pub fn eval<T: Elementary>(&self, x: &T) -> (T, T) {
+        _UOp::from(*self).eval(x)
+    }
+}
+impl BinaryKind {
+    /// Returns the output and the partial derivatives at `(l, r)`.
+    #[inline]
+    pub fn eval<T: Elementary>(&self, l: &T, r: &T) -> (T, T, T) {
+        _BOp::from(*self).eval(l, r)
+    }
+}
+
+/// Conversion between expressions and [`Graph`].
+///
+/// Nodes are identified by their addresses, which are stable during the conversion
+/// since all of them are owned by the expressions being flattened.
+pub(crate) struct _Graph<T> {
+    nodes: Vec<Node<T>>,
+    exprs: HashMap<*const _Expr<T>, usize>,
+    /// keyed by the address of a tape and the index of an entry
+    entries: HashMap<(usize, usize), usize>,
+    vars: HashMap<Id, usize>,
+}
+
+impl<T: Clone> _Graph<T> {
+    pub fn flatten<'a>(roots: impl IntoIterator<Item = &'a _Expr<T>>) -> Graph<T>
+    where
+        T: 'a,
+    {
+        let mut this = Self {
+            nodes: Vec::new(),
+            exprs: HashMap::new(),
+            entries: HashMap::new(),
+            vars: HashMap::new(),
+        };
+        let outputs = roots.into_iter().map(|e| this.visit(e)).collect();
+        Graph::from_parts(this.nodes, outputs)
+    }
+
+    #[inline]
+    fn push(&mut self, node: Node<T>) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    #[inline]
+    fn push_input(&mut self, i: &_In<_Rc<_Expr<T>>, T>) -> (usize, usize) {
+        let idx = |e: &_Rc<_Expr<T>>| self.exprs[&_Rc::as_ptr(e)];
+        match i {
+            _In::L { l, r } => {
+                let l = idx(l);
+                (l, self.push(Node::Const { o: r.clone() }))
+            }
+            _In::R { l, r } => {
+                let r = idx(r);
+                (self.push(Node::Const { o: l.clone() }), r)
+            }
+            _In::LR { l, r } => (idx(l), idx(r)),
+        }
+    }
+
+    /// Post-order traversal without recursion.
+    fn visit(&mut self, root: &_Expr<T>) -> usize {
+        let mut stack = vec![(root, false)];
+        while let Some((e, expanded)) = stack.pop() {
+            let key = e as *const _Expr<T>;
+            if self.exprs.contains_key(&key) {
+                continue;
+            }
+            let idx = match e {
+                _Expr::_OnlyForDrop => unreachable!(),
+                _Expr::Leaf(_Leaf::Var(v)) => match self.vars.get(v.id()) {
+                    Some(idx) => *idx,
+                    None => {
+                        let node = Node::Var {
+                            id: v.id().clone(),
+                            o: v.val().clone(),
+                        };
+                        let idx = self.push(node);
+                        self.vars.insert(v.id().clone(), idx);
+                        idx
+                    }
+                },
+                _Expr::Leaf(_Leaf::Const(c)) => self.push(Node::Const { o: c.clone() }),
+                _Expr::Compressed { g, o, gs } => self.push(Node::Compressed {
+                    g: *g,
+                    o: o.clone(),
+                    gs: gs.iter().map(|(id, g)| (id.clone(), g.clone())).collect(),
+                }),
+                _Expr::Taped(t) => self.visit_tape(t),
+                _Expr::Node(_, n) if !expanded => {
+                    stack.push((e, true));
+                    match n {
+                        _Node::Unary(u) => stack.push((u.input(), false)),
+                        _Node::Binary(b) => match b.inputs() {
+                            _In::L { l, .. } => stack.push((l, false)),
+                            _In::R { r, .. } => stack.push((r, false)),
+                            _In::LR { l, r } => {
+                                stack.push((r, false));
+                                stack.push((l, false));
+                            }
+                        },
+                        _Node::Select(s) => {
+                            stack.extend(s.inputs().into_iter().rev().map(|i| (i, false)))
+                        }
+                    }
+                    continue;
+                }
+                _Expr::Node(_, _Node::Unary(u)) => {
+                    let i = self.exprs[&(u.input() as *const _)];
+                    let node = u.op().node(i, u.output().clone(), u.partial().clone());
+                    self.push(node)
+                }
+                _Expr::Node(_, _Node::Binary(b)) => {
+                    let (l, r) = self.push_input(b.inputs());
+                    let (gl, gr) = b.partials();
+                    let node = b
+                        .op()
+                        .node(l, r, b.output().clone(), gl.clone(), gr.clone());
+                    self.push(node)
+                }
+                _Expr::Node(_, _Node::Select(s)) => {
+                    let [x, y, a, b] = s.inputs().map(|i| self.exprs[&(i as *const _)]);
+                    let node = Node::Select {
+                        cmp: s.cmp(),
+                        x,
+                        y,
+                        a,
+                        b,
+                        o: s.output().clone(),
+                        cond: s.cond(),
+                    };
+                    self.push(node)
+                }
+            };
+            self.exprs.insert(key, idx);
+        }
+        self.exprs[&(root as *const _)]
+    }
+
+    /// Flattens entries of a tape on which `t` depends.
+    fn visit_tape(&mut self, t: &_Taped<T>) -> usize {
+        let tape_key = _Rc::as_ptr(t.tape()) as usize;
+        let tape = t.tape().borrow();
+        let entries = tape.entries();
+
+        let mut required = vec![false; t.idx() + 1];
+        required[t.idx()] = true;
+        for (i, entry) in entries[..=t.idx()].iter().enumerate().rev() {
+            if !required[i] {
+                continue;
+            }
+            match &entry.k {
+                _Kind::Unary { i, .. } => required[*i] = true,
+                _Kind::Binary { i, .. } => match i {
+                    _In::L { l, .. } => required[*l] = true,
+                    _In::R { r, .. } => required[*r] = true,
+                    _In::LR { l, r } => {
+                        required[*l] = true;
+                        required[*r] = true;
+                    }
+                },
+                _Kind::Select { x, y, a, b, .. } => {
+                    for j in [x, y, a, b] {
+                        required[*j] = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (i, entry) in entries[..=t.idx()].iter().enumerate() {
+            if !required[i] || self.entries.contains_key(&(tape_key, i)) {
+                continue;
+            }
+            let idx = |j: &usize| self.entries[&(tape_key, *j)];
+            let node = match &entry.k {
+                _Kind::Var(id) => match self.vars.get(id) {
+                    Some(idx) => {
+                        self.entries.insert((tape_key, i), *idx);
+                        continue;
+                    }
+                    None => Node::Var {
+                        id: id.clone(),
+                        o: entry.o.clone(),
+                    },
+                },
+                _Kind::Ext(e) => {
+                    // tree expressions never contain taped ones,
+                    // so this recursion is at most one level.
+                    let idx = self.visit(e);
+                    self.entries.insert((tape_key, i), idx);
+                    continue;
+                }
+                _Kind::Unary { i, g, op } => op.node(idx(i), entry.o.clone(), g.clone()),
+                _Kind::Binary { i, gl, gr, op } => {
+                    let (l, r) = match i {
+                        _In::L { l, r } => {
+                            let l = idx(l);
+                            (l, self.push(Node::Const { o: r.clone() }))
+                        }
+                        _In::R { l, r } => {
+                            let r = idx(r);
+                            (self.push(Node::Const { o: l.clone() }), r)
+                        }
+                        _In::LR { l, r } => (idx(l), idx(r)),
+                    };
+                    op.node(l, r, entry.o.clone(), gl.clone(), gr.clone())
+                }
+                _Kind::Select {
+                    cmp,
+                    x,
+                    y,
+                    a,
+                    b,
+                    cond,
+                } => Node::Select {
+                    cmp: *cmp,
+                    x: idx(x),
+                    y: idx(y),
+                    a: idx(a),
+                    b: idx(b),
+                    o: entry.o.clone(),
+                    cond: *cond,
+                },
+            };
+            if let Node::Var { id, .. } = &node {
+                self.vars.insert(id.clone(), self.nodes.len());
+            }
+            let idx = self.push(node);
+            self.entries.insert((tape_key, i), idx);
+        }
+        self.entries[&(tape_key, t.idx())]
+    }
+
+    pub fn restore(graph: &Graph<T>) -> Result<Vec<Expr<T>>, GraphError> {
+        let mut exprs: Vec<Expr<T>> = Vec::with_capacity(graph.nodes().len());
+        for (node, n) in graph.nodes().iter().enumerate() {
+            let input = |input: &usize| match exprs.get(*input) {
+                Some(e) => Ok(e.clone()),
+                None => Err(GraphError::InvalidInput {
+                    node,
+                    input: *input,
+                }),
+            };
+            let e = match n {
+                Node::Var { id, o } => Var::from_parts(id.clone(), o.clone()).into_expr(),
+                Node::Const { o } => Expr::constant(o.clone()),
+                Node::Unary { op, i, o, g } => {
+                    _Unary::create(input(i)?, o.clone(), g.clone(), (*op).into())
+                }
+                Node::Binary {
+                    op,
+                    l,
+                    r,
+                    o,
+                    gl,
+                    gr,
+                } => _Binary::create(
+                    input(l)?,
+                    input(r)?,
+                    o.clone(),
+                    gl.clone(),
+                    gr.clone(),
+                    (*op).into(),
+                ),
+                Node::CustomUnary { op, i, o, g } => {
+                    let op = _UOp::Custom(op.clone());
+                    _Unary::create(input(i)?, o.clone(), g.clone(), op)
+                }
+                Node::CustomBinary {
+                    op,
+                    l,
+                    r,
+                    o,
+                    gl,
+                    gr,
+                } => {
+                    let op = _BOp::Custom(op.clone());
+                    _Binary::create(input(l)?, input(r)?, o.clone(), gl.clone(), gr.clone(), op)
+                }
+                Node::Compressed { g, o, gs } => _Expr::Compressed {
+                    g: *g,
+                    o: o.clone(),
+                    gs: _Rc::new(gs.iter().cloned().collect()),
+                }
+                .into(),
+                Node::Select {
+                    cmp,
+                    x,
+                    y,
+                    a,
+                    b,
+                    cond,
+                    ..
+                } => _Select::create(*cmp, input(x)?, input(y)?, input(a)?, input(b)?, *cond),
+            };
+            exprs.push(e);
+        }
+        graph
+            .outputs()
+            .iter()
+            .map(|i| exprs.get(*i).cloned().ok_or(GraphError::InvalidOutput(*i)))
+            .collect()
+    }
+}
@@ -4,7 +4,7 @@ use derivative::Derivative;
 
 use crate::scalar::Scalar;
 
-use super::{_Expr, _binary::_Binary, _unary::_Unary};
+use super::{_Expr, _adjoint::_Adjoint, _binary::_Binary, _select::_Select, _unary::_Unary};
 
 #[derive(Clone, Derivative)]
 #[derivative(Debug)]
@@ -13,6 +13,8 @@ pub(crate) enum _Node<T> {
     Unary(_Unary<T>),
     #[derivative(Debug = "transparent")]
     Binary(_Binary<T>),
+    #[derivative(Debug = "transparent")]
+    Select(_Select<T>),
 }
 
 impl<T> _Node<T> {
@@ -21,6 +23,7 @@ impl<T> _Node<T> {
         match self {
             Self::Unary(u) => u.output(),
             Self::Binary(b) => b.output(),
+            Self::Select(s) => s.output(),
         }
     }
     pub fn _take_expr_to_back_for_drop(&mut self, buf: &mut VecDeque<_Expr<T>>) {
@@ -40,16 +43,23 @@ impl<T> _Node<T> {
                     buf.push_back(std::mem::replace(r, _Expr::_OnlyForDrop));
                 }
             }
+            _Node::Select(s) => {
+                for i in s._ref_expr_for_drop().into_iter().flatten() {
+                    buf.push_back(std::mem::replace(i, _Expr::_OnlyForDrop));
+                }
+            }
         }
     }
 }
 
 impl<T: Scalar> _Node<T> {
+    /// Passes `grad` times the local partial to each non-constant input.
     #[inline]
-    pub fn push_grads<'a>(&'a self, grads: &mut VecDeque<(&'a _Expr<T>, T)>, grad: T) {
+    pub fn backprop<'a, A: _Adjoint<T>>(&'a self, grad: A, f: impl FnMut(&'a _Expr<T>, A)) {
         match self {
-            Self::Unary(u) => u.push_grads(grads, grad),
-            Self::Binary(b) => b.push_grads(grads, grad),
+            Self::Unary(u) => u.backprop(grad, f),
+            Self::Binary(b) => b.backprop(grad, f),
+            Self::Select(s) => s.backprop(grad, f),
         }
     }
 }
@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    graph::{CmpKind, Node},
+    scalar::{Elementary, Scalar},
+    Graph, Id,
+};
+
+use super::{_binary::_BOp, _unary::_UOp};
+
+/// Instruction of a compiled program.
+///
+/// Each instruction writes its result into the register with the same index,
+/// and operands are indices of preceding registers.
+#[derive(Debug, Clone)]
+pub(crate) enum _Inst<T> {
+    /// Loads the input with the index.
+    Input(usize),
+    /// Constants are loaded when the program is created.
+    Const,
+    Unary(_UOp<T>, usize),
+    Binary(_BOp<T>, usize, usize),
+    /// Compressed expression, whose value is fixed.
+    /// Derivatives are given by indices of inputs.
+    Frozen(Vec<(usize, T)>),
+    /// Compares the first two operands and loads one of the others.
+    /// The first partial derivative is one if the former is loaded and zero otherwise.
+    Select(CmpKind, usize, usize, usize, usize),
+}
+
+impl<T: Clone> _Inst<T> {
+    /// Translates nodes into instructions.
+    ///
+    /// Returns inputs sorted by [`Id`], instructions and initial values of registers.
+    pub fn compile(graph: &Graph<T>) -> (Vec<Id>, Vec<Self>, Vec<T>) {
+        let mut ids = BTreeSet::new();
+        for n in graph.nodes() {
+            match n {
+                Node::Var { id, .. } => {
+                    ids.insert(id);
+                }
+                Node::Compressed { gs, .. } => ids.extend(gs.iter().map(|(id, _)| id)),
+                _ => {}
+            }
+        }
+        let ids: Vec<_> = ids.into_iter().cloned().collect();
+        let input = |id: &Id| ids.binary_search(id).unwrap();
+
+        let insts = graph
+            .nodes()
+            .iter()
+            .map(|n| match n {
+                Node::Var { id, .. } => _Inst::Input(input(id)),
+                Node::Const { .. } => _Inst::Const,
+                Node::Unary { op, i, .. } => _Inst::Unary((*op).into(), *i),
+                Node::Binary { op, l, r, .. } => _Inst::Binary((*op).into(), *l, *r),
+                Node::CustomUnary { op, i, .. } => _Inst::Unary(_UOp::Custom(op.clone()), *i),
+                Node::CustomBinary { op, l, r, .. } => {
+                    _Inst::Binary(_BOp::Custom(op.clone()), *l, *r)
+                }
+                Node::Compressed { gs, .. } => {
+                    _Inst::Frozen(gs.iter().map(|(id, g)| (input(id), g.clone())).collect())
+                }
+                Node::Select {
+                    cmp, x, y, a, b, ..
+                } => _Inst::Select(*cmp, *x, *y, *a, *b),
+            })
+            .collect();
+        let regs = graph.nodes().iter().map(|n| n.output().clone()).collect();
+        (ids, insts, regs)
+    }
+}
+
+impl<T: Elementary> _Inst<T> {
+    /// Evaluates the instruction and returns the output and partial derivatives.
+    #[inline]
+    pub fn forward(&self, inputs: &[T], regs: &[T]) -> Option<(T, T, T)> {
+        match self {
+            _Inst::Input(k) => Some((inputs[*k].clone(), T::one(), T::zero())),
+            _Inst::Const | _Inst::Frozen(_) => None,
+            _Inst::Unary(op, i) => {
+                let (o, g) = op.eval(&regs[*i]);
+                Some((o, g, T::zero()))
+            }
+            _Inst::Binary(op, l, r) => Some(op.eval(&regs[*l], &regs[*r])),
+            _Inst::Select(cmp, x, y, a, b) => Some(match cmp.eval(&regs[*x], &regs[*y]) {
+                true => (regs[*a].clone(), T::one(), T::zero()),
+                false => (regs[*b].clone(), T::zero(), T::zero()),
+            }),
+        }
+    }
+}
+
+impl<T: Scalar> _Inst<T> {
+    /// Propagates the adjoint of this instruction to its operands.
+    #[inline]
+    pub fn backward(&self, adj: &T, gl: &T, gr: &T, adjoints: &mut [T], grad: &mut [T]) {
+        match self {
+            _Inst::Input(k) => grad[*k] += adj,
+            _Inst::Const => {}
+            _Inst::Unary(_, i) => adjoints[*i] += &(adj.clone() * gl),
+            _Inst::Binary(_, l, r) => {
+                adjoints[*l] += &(adj.clone() * gl);
+                adjoints[*r] += &(adj.clone() * gr);
+            }
+            _Inst::Frozen(gs) => {
+                for (k, g) in gs {
+                    grad[*k] += &(adj.clone() * g);
+                }
+            }
+            _Inst::Select(.., a, b) => match gl.is_zero() {
+                true => adjoints[*b] += adj,
+                false => adjoints[*a] += adj,
+            },
+        }
+    }
+}
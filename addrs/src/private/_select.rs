@@ -0,0 +1,98 @@
+use crate::{graph::CmpKind, scalar::Scalar, Expr};
+
+use super::{_adjoint::_Adjoint, _expr::_Expr, _node::_Node, _sync::_Rc, _tape::_Tape};
+
+/// Node taking one of two branches by a comparison of two expressions.
+///
+/// Operands of the comparison are kept so that the branch can be decided again
+/// when the graph is evaluated at other values.
+#[derive(Debug, Clone)]
+pub(crate) struct _Select<T> {
+    x: _Rc<_Expr<T>>,
+    y: _Rc<_Expr<T>>,
+    a: _Rc<_Expr<T>>,
+    b: _Rc<_Expr<T>>,
+    cmp: CmpKind,
+    cond: bool,
+    o: T,
+}
+
+impl<T: Clone> _Select<T> {
+    pub fn create(
+        cmp: CmpKind,
+        x: Expr<T>,
+        y: Expr<T>,
+        a: Expr<T>,
+        b: Expr<T>,
+        cond: bool,
+    ) -> Expr<T> {
+        // the branch never changes if the comparison does not depend on variables
+        if x._is_const() && y._is_const() {
+            return if cond { a } else { b };
+        }
+        if [&x, &y, &a, &b].iter().any(|e| e._is_taped()) {
+            let (x, y, a, b) = (x._take(), y._take(), a._take(), b._take());
+            return _Tape::record_select(cmp, [x, y, a, b], cond).into();
+        }
+        let o = if cond { a.output() } else { b.output() }.clone();
+        let [x, y, a, b] = [x, y, a, b].map(|e| _Rc::new(e._take()));
+        let gen = [&x, &y, &a, &b]
+            .iter()
+            .map(|e| e.generation())
+            .max()
+            .unwrap();
+        let s = _Select {
+            x,
+            y,
+            a,
+            b,
+            cmp,
+            cond,
+            o,
+        };
+        _Expr::Node(gen + 1, _Node::Select(s)).into()
+    }
+}
+
+impl<T> _Select<T> {
+    #[inline]
+    pub fn output(&self) -> &T {
+        &self.o
+    }
+    #[inline]
+    pub fn cmp(&self) -> CmpKind {
+        self.cmp
+    }
+    /// Whether the first branch is taken.
+    #[inline]
+    pub fn cond(&self) -> bool {
+        self.cond
+    }
+    /// Operands of the comparison followed by the branches.
+    #[inline]
+    pub fn inputs(&self) -> [&_Expr<T>; 4] {
+        [&self.x, &self.y, &self.a, &self.b].map(|e| e.as_ref())
+    }
+    #[inline]
+    pub fn taken(&self) -> &_Expr<T> {
+        match self.cond {
+            true => &self.a,
+            false => &self.b,
+        }
+    }
+    #[inline]
+    pub fn _ref_expr_for_drop(&mut self) -> [Option<&mut _Expr<T>>; 4] {
+        [&mut self.x, &mut self.y, &mut self.a, &mut self.b].map(_Rc::get_mut)
+    }
+}
+
+impl<T: Scalar> _Select<T> {
+    /// Passes `grad` as it is to the branch taken.
+    #[inline]
+    pub fn backprop<'a, A: _Adjoint<T>>(&'a self, grad: A, mut f: impl FnMut(&'a _Expr<T>, A)) {
+        let e = self.taken();
+        if !e.is_const() {
+            f(e, grad);
+        }
+    }
+}
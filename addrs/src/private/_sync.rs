@@ -0,0 +1,65 @@
+//! Shared ownership switched by the `sync` feature.
+//!
+//! Without the feature, cheaper single-threaded `Rc` and `RefCell` are used.
+//! With the feature, `Arc` and `Mutex` are used so that expressions are `Send + Sync`.
+
+use std::ops::{Deref, DerefMut};
+
+#[cfg(not(feature = "sync"))]
+pub(crate) use std::rc::Rc as _Rc;
+#[cfg(feature = "sync")]
+pub(crate) use std::sync::Arc as _Rc;
+
+#[cfg(not(feature = "sync"))]
+#[derive(Debug)]
+pub(crate) struct _Cell<T>(std::cell::RefCell<T>);
+
+#[cfg(not(feature = "sync"))]
+impl<T> _Cell<T> {
+    #[inline]
+    pub fn new(val: T) -> Self {
+        Self(std::cell::RefCell::new(val))
+    }
+    #[inline]
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.0.borrow()
+    }
+    #[inline]
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.0.borrow_mut()
+    }
+}
+
+#[cfg(feature = "sync")]
+#[derive(Debug)]
+pub(crate) struct _Cell<T>(std::sync::Mutex<T>);
+
+#[cfg(feature = "sync")]
+impl<T> _Cell<T> {
+    #[inline]
+    pub fn new(val: T) -> Self {
+        Self(std::sync::Mutex::new(val))
+    }
+    #[inline]
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.0.lock().unwrap()
+    }
+    #[inline]
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.0.lock().unwrap()
+    }
+}
+
+/// `Send + Sync` with the `sync` feature and no bound otherwise,
+/// so that values shared by expressions may be single-threaded without the feature.
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+/// `Send + Sync` with the `sync` feature and no bound otherwise,
+/// so that values shared by expressions may be single-threaded without the feature.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
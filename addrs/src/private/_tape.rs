@@ -0,0 +1,276 @@
+use derivative::Derivative;
+
+use crate::{graph::CmpKind, scalar::Scalar, var::Id, Expr};
+
+use super::{
+    _adjoint::{_Adjoint, _Sink},
+    _binary::{_BOp, _In},
+    _expr::_Expr,
+    _leaf::_Leaf,
+    _sync::{_Cell, _Rc},
+    _unary::_UOp,
+};
+
+#[derive(Debug)]
+pub(crate) enum _Kind<T> {
+    /// Variable registered to the tape.
+    Var(Id),
+    /// Expression built outside of the tape and used as an input of a recorded operation.
+    Ext(_Rc<_Expr<T>>),
+    Unary {
+        i: usize,
+        g: T,
+        op: _UOp<T>,
+    },
+    Binary {
+        i: _In<usize, T>,
+        gl: T,
+        gr: T,
+        op: _BOp<T>,
+    },
+    Select {
+        cmp: CmpKind,
+        x: usize,
+        y: usize,
+        a: usize,
+        b: usize,
+        cond: bool,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct _Entry<T> {
+    pub o: T,
+    pub k: _Kind<T>,
+}
+
+/// Flat list of recorded operations.
+///
+/// Inputs of an entry are referred by indices and always precede the entry itself.
+/// Hence, a reverse scan over the entries is a valid backward pass.
+#[derive(Debug)]
+pub(crate) struct _Tape<T> {
+    entries: Vec<_Entry<T>>,
+}
+
+pub(crate) type _TapeRef<T> = _Rc<_Cell<_Tape<T>>>;
+
+/// Expression recorded on a tape.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub(crate) struct _Taped<T> {
+    #[derivative(Debug = "ignore")]
+    tape: _TapeRef<T>,
+    idx: usize,
+    gen: usize,
+    o: T,
+}
+
+impl<T> _Tape<T> {
+    #[inline]
+    pub fn new() -> _TapeRef<T> {
+        _Rc::new(_Cell::new(Self {
+            entries: Vec::new(),
+        }))
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[inline]
+    pub fn entries(&self) -> &[_Entry<T>] {
+        &self.entries
+    }
+    #[inline]
+    fn push(&mut self, o: T, k: _Kind<T>) -> usize {
+        self.entries.push(_Entry { o, k });
+        self.entries.len() - 1
+    }
+}
+
+impl<T: Clone> _Tape<T> {
+    pub fn var(tape: &_TapeRef<T>, id: Id, o: T) -> _Expr<T> {
+        let idx = tape.borrow_mut().push(o.clone(), _Kind::Var(id));
+        _Expr::Taped(_Taped {
+            tape: tape.clone(),
+            idx,
+            gen: 0,
+            o,
+        })
+    }
+
+    /// Returns index of the entry corresponding to a non-constant expression.
+    /// Expressions which are not recorded on this tape are registered as new entries.
+    fn register(tape: &_TapeRef<T>, e: _Expr<T>) -> (usize, usize) {
+        match e {
+            _Expr::Taped(t) => {
+                assert!(
+                    _Rc::ptr_eq(tape, &t.tape),
+                    "expressions recorded on different tapes cannot be mixed"
+                );
+                (t.idx, t.gen)
+            }
+            _Expr::Leaf(_Leaf::Var(v)) => {
+                let (id, o) = v.into_parts();
+                let idx = tape.borrow_mut().push(o, _Kind::Var(id));
+                (idx, 0)
+            }
+            e => {
+                let gen = e.generation();
+                let o = e.output().clone();
+                let idx = tape.borrow_mut().push(o, _Kind::Ext(_Rc::new(e)));
+                (idx, gen)
+            }
+        }
+    }
+
+    pub fn record_unary(i: _Expr<T>, o: T, g: T, op: _UOp<T>) -> _Expr<T> {
+        let _Expr::Taped(i) = i else { unreachable!() };
+        let k = _Kind::Unary { i: i.idx, g, op };
+        let idx = i.tape.borrow_mut().push(o.clone(), k);
+        _Expr::Taped(_Taped {
+            tape: i.tape,
+            idx,
+            gen: i.gen + 1,
+            o,
+        })
+    }
+
+    pub fn record_binary(l: _Expr<T>, r: _Expr<T>, o: T, gl: T, gr: T, op: _BOp<T>) -> _Expr<T> {
+        let tape = match (&l, &r) {
+            (_Expr::Taped(t), _) | (_, _Expr::Taped(t)) => t.tape.clone(),
+            _ => unreachable!(),
+        };
+        let (gen, i) = match (l.is_const(), r.is_const()) {
+            (true, false) => {
+                let l = l.output().clone();
+                let (r, gen) = Self::register(&tape, r);
+                (gen + 1, _In::R { l, r })
+            }
+            (false, true) => {
+                let (l, gen) = Self::register(&tape, l);
+                let r = r.output().clone();
+                (gen + 1, _In::L { l, r })
+            }
+            _ => {
+                let (l, lgen) = Self::register(&tape, l);
+                let (r, rgen) = Self::register(&tape, r);
+                (lgen.max(rgen) + 1, _In::LR { l, r })
+            }
+        };
+        let k = _Kind::Binary { i, gl, gr, op };
+        let idx = tape.borrow_mut().push(o.clone(), k);
+        _Expr::Taped(_Taped { tape, idx, gen, o })
+    }
+
+    /// `inputs` are operands of the comparison followed by the branches.
+    pub fn record_select(cmp: CmpKind, inputs: [_Expr<T>; 4], cond: bool) -> _Expr<T> {
+        let tape = inputs
+            .iter()
+            .find_map(|e| match e {
+                _Expr::Taped(t) => Some(t.tape.clone()),
+                _ => None,
+            })
+            .unwrap();
+        let [_, _, a, b] = &inputs;
+        let o = if cond { a.output() } else { b.output() }.clone();
+        let [(x, xg), (y, yg), (a, ag), (b, bg)] = inputs.map(|e| Self::register(&tape, e));
+        let gen = xg.max(yg).max(ag).max(bg) + 1;
+        let k = _Kind::Select {
+            cmp,
+            x,
+            y,
+            a,
+            b,
+            cond,
+        };
+        let idx = tape.borrow_mut().push(o.clone(), k);
+        _Expr::Taped(_Taped { tape, idx, gen, o })
+    }
+}
+
+impl<T: Scalar> _Tape<T> {
+    /// Linear reverse scan seeded at several entries.
+    fn backprop<A: _Adjoint<T>>(&self, seeds: Vec<(usize, A)>, res: &mut impl _Sink<T, A>) {
+        let Some(last) = seeds.iter().map(|(i, _)| *i).max() else {
+            return;
+        };
+        let mut adjoints: Vec<Option<A>> = Vec::new();
+        adjoints.resize_with(last + 1, || None);
+        let acc = |adjoints: &mut [Option<A>], j: usize, g: A| match &mut adjoints[j] {
+            Some(x) => x.merge(g),
+            x => *x = Some(g),
+        };
+        for (i, seed) in seeds {
+            acc(&mut adjoints, i, seed);
+        }
+        let mut exts = Vec::new();
+        for (i, entry) in self.entries[..=last].iter().enumerate().rev() {
+            let Some(grad) = adjoints[i].take() else {
+                continue;
+            };
+            match &entry.k {
+                _Kind::Var(id) => res.add(id, grad),
+                _Kind::Ext(e) => exts.push((e.as_ref(), grad)),
+                _Kind::Unary { i, g, .. } => acc(&mut adjoints, *i, grad.scaled(g)),
+                _Kind::Binary { i, gl, gr, .. } => {
+                    i.backprop(grad, gl, gr, |j, g| acc(&mut adjoints, *j, g))
+                }
+                _Kind::Select { a, b, cond, .. } => {
+                    acc(&mut adjoints, if *cond { *a } else { *b }, grad)
+                }
+            }
+        }
+        _Expr::backward(exts, res);
+    }
+}
+
+impl<T> Drop for _Tape<T> {
+    fn drop(&mut self) {
+        // external expressions may be deep trees.
+        // drop them through `Expr` to avoid recursion.
+        for entry in self.entries.iter_mut() {
+            if let _Kind::Ext(e) = &mut entry.k {
+                if let Some(e) = _Rc::get_mut(e) {
+                    drop(Expr::from(std::mem::replace(e, _Expr::_OnlyForDrop)));
+                }
+            }
+        }
+    }
+}
+
+impl<T> _Taped<T> {
+    #[inline]
+    pub fn output(&self) -> &T {
+        &self.o
+    }
+    #[inline]
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+    #[inline]
+    pub fn tape(&self) -> &_TapeRef<T> {
+        &self.tape
+    }
+    #[inline]
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+}
+
+impl<T: Scalar> _Taped<T> {
+    /// Reverse sweep from expressions recorded on tapes.
+    /// Each tape is scanned only once.
+    pub fn backprop<A: _Adjoint<T>>(roots: Vec<(&Self, A)>, res: &mut impl _Sink<T, A>) {
+        let mut tapes: Vec<(&_TapeRef<T>, Vec<_>)> = Vec::new();
+        for (root, seed) in roots {
+            match tapes.iter_mut().find(|(t, _)| _Rc::ptr_eq(t, &root.tape)) {
+                Some((_, seeds)) => seeds.push((root.idx, seed)),
+                None => tapes.push((&root.tape, vec![(root.idx, seed)])),
+            }
+        }
+        for (tape, seeds) in tapes {
+            tape.borrow().backprop(seeds, res);
+        }
+    }
+}
@@ -1,56 +1,236 @@
+mod abs;
+mod acos;
+mod asin;
+mod asinh;
+mod atan;
+mod cbrt;
+mod cos;
+mod cosh;
+mod custom;
+mod erf;
+mod erfc;
 mod exp;
+mod exp2;
+mod exp_m1;
+mod ln_1p;
 mod log;
+mod log10;
+mod log2;
 mod neg;
+mod norm_cdf;
+mod norm_inv_cdf;
+mod norm_pdf;
+mod powi;
+mod relu;
+mod sin;
+mod sinh;
 mod sqrt;
+mod tan;
+mod tanh;
 
-use std::{collections::VecDeque, rc::Rc};
+use crate::{
+    custom::CustomUnary,
+    expr::Expr,
+    scalar::{Elementary, Scalar, Subgradient},
+};
 
-use crate::{expr::Expr, scalar::Scalar};
-
-use super::{_expr::_Expr, _node::_Node};
+use super::{_adjoint::_Adjoint, _expr::_Expr, _node::_Node, _sync::_Rc, _tape::_Tape};
 
 #[derive(Debug, Clone)]
-enum _UOp {
+pub(crate) enum _UOp<T> {
     Neg,
     Sqrt,
     Exp,
     Log,
+    Powi(i32),
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Tanh,
+    Sinh,
+    Cosh,
+    Asinh,
+    Ln1p,
+    ExpM1,
+    Log2,
+    Log10,
+    Exp2,
+    Cbrt,
+    Erf,
+    Erfc,
+    NormPdf,
+    NormCdf,
+    NormInvCdf,
+    Abs(Subgradient),
+    Relu(Subgradient),
+    Custom(CustomUnary<T>),
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct _Unary<T> {
-    i: Rc<_Expr<T>>,
+    i: _Rc<_Expr<T>>,
     o: T,
     g: T,
-    #[allow(dead_code)]
-    op: _UOp,
+    op: _UOp<T>,
 }
 
-impl<T> _Unary<T> {
+impl<T: Clone> _Unary<T> {
     #[inline]
-    fn create(i: Expr<T>, o: T, g: T, op: _UOp) -> Expr<T> {
+    pub(super) fn create(i: Expr<T>, o: T, g: T, op: _UOp<T>) -> Expr<T> {
         if i._is_const() {
             return Expr::constant(o);
         }
-        let i = Rc::new(i._take());
+        if i._is_taped() {
+            return _Tape::record_unary(i._take(), o, g, op).into();
+        }
+        let i = _Rc::new(i._take());
         let gen = i.generation() + 1;
         let u = _Unary { i, o, g, op };
         _Expr::Node(gen, _Node::Unary(u)).into()
     }
+}
+
+impl<T: Elementary> _UOp<T> {
+    /// Returns the output and the partial derivative at `x`.
+    #[inline]
+    pub fn eval(&self, x: &T) -> (T, T) {
+        match self {
+            _UOp::Neg => neg::eval(x),
+            _UOp::Sqrt => sqrt::eval(x),
+            _UOp::Exp => exp::eval(x),
+            _UOp::Log => log::eval(x),
+            _UOp::Powi(n) => powi::eval(x, *n),
+            _UOp::Sin => sin::eval(x),
+            _UOp::Cos => cos::eval(x),
+            _UOp::Tan => tan::eval(x),
+            _UOp::Asin => asin::eval(x),
+            _UOp::Acos => acos::eval(x),
+            _UOp::Atan => atan::eval(x),
+            _UOp::Tanh => tanh::eval(x),
+            _UOp::Sinh => sinh::eval(x),
+            _UOp::Cosh => cosh::eval(x),
+            _UOp::Asinh => asinh::eval(x),
+            _UOp::Ln1p => ln_1p::eval(x),
+            _UOp::ExpM1 => exp_m1::eval(x),
+            _UOp::Log2 => log2::eval(x),
+            _UOp::Log10 => log10::eval(x),
+            _UOp::Exp2 => exp2::eval(x),
+            _UOp::Cbrt => cbrt::eval(x),
+            _UOp::Erf => erf::eval(x),
+            _UOp::Erfc => erfc::eval(x),
+            _UOp::NormPdf => norm_pdf::eval(x),
+            _UOp::NormCdf => norm_cdf::eval(x),
+            _UOp::NormInvCdf => norm_inv_cdf::eval(x),
+            _UOp::Abs(p) => abs::eval(x, *p),
+            _UOp::Relu(p) => relu::eval(x, *p),
+            _UOp::Custom(c) => c.eval(x),
+        }
+    }
+}
 
+impl<T> _Unary<T> {
     #[inline]
     pub fn output(&self) -> &T {
         &self.o
     }
     #[inline]
+    pub fn input(&self) -> &_Expr<T> {
+        &self.i
+    }
+    #[inline]
+    pub fn partial(&self) -> &T {
+        &self.g
+    }
+    #[inline]
+    pub fn op(&self) -> &_UOp<T> {
+        &self.op
+    }
+    #[inline]
     pub fn _ref_expr_for_drop(&mut self) -> Option<&mut _Expr<T>> {
-        Rc::get_mut(&mut self.i)
+        _Rc::get_mut(&mut self.i)
     }
 }
 impl<T: Scalar> _Unary<T> {
-    pub fn push_grads<'a>(&'a self, grads: &mut VecDeque<(&'a _Expr<T>, T)>, grad: T) {
+    #[inline]
+    pub fn backprop<'a, A: _Adjoint<T>>(&'a self, grad: A, mut f: impl FnMut(&'a _Expr<T>, A)) {
         if !self.i.is_const() {
-            grads.push_back((&self.i, grad * &self.g));
+            f(&self.i, grad.scaled(&self.g));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::assert_gradients,
+        scalar::{
+            Acos, Asin, Asinh, Atan, Atan2, Cbrt, Cos, Cosh, Erf, Erfc, Exp2, ExpM1, Ln1p, Log10,
+            Log2, NormCdf, NormInvCdf, NormPdf, Sin, Sinh, Tan, Tanh,
+        },
+        Graph, VarGroup,
+    };
+
+    use super::*;
+
+    fn trig<S: Scalar + Sin + Cos + Tan + Asin + Acos + Atan + Atan2>(x: &[S]) -> S {
+        let a = x[0].clone().sin() * &x[1].clone().cos() + &x[0].clone().tan();
+        let b = x[2].clone().asin() - &(x[2].clone().acos() * &x[1].clone().atan());
+        a * &b + &x[1].clone().atan2(x[0].clone() - &x[2])
+    }
+
+    #[test]
+    fn test_trigonometric() {
+        assert_gradients(&[0.3, -1.2, 0.4], trig, trig);
+    }
+
+    fn f<S: Scalar + Tanh + Sinh + Cosh + Asinh + Ln1p + ExpM1 + Log2 + Log10 + Exp2 + Cbrt>(
+        x: &[S],
+    ) -> S {
+        let a = x[0].clone().tanh() * &x[1].clone().sinh() + &x[1].clone().cosh().log2();
+        let b = x[0].clone().asinh() * &x[1].clone().ln_1p() - &x[0].clone().exp_m1();
+        a * &b + &(x[1].clone().log10() * &x[0].clone().exp2()) - &x[0].clone().cbrt()
+    }
+
+    #[test]
+    fn test_hyperbolic_and_exponential() {
+        assert_gradients(&[0.3, 1.2], f, f);
+
+        // accurate near zero
+        let grp = VarGroup::new("x");
+        let r = grp.val(1e-12_f64).into_expr();
+        let e = r.clone().ln_1p();
+        assert_eq!(
+            (*e.output(), e.grads()[&r]),
+            (1e-12_f64.ln_1p(), 1.0 / (1.0 + 1e-12))
+        );
+        let e = r.clone().exp_m1();
+        assert_eq!(*e.output(), 1e-12_f64.exp_m1());
+    }
+
+    fn g<S: Scalar + Erf + Erfc + NormPdf + NormCdf + NormInvCdf>(x: &[S]) -> S {
+        let a = x[0].clone().erf() * &x[1].clone().erfc() + &x[1].clone().norm_pdf();
+        a * &x[0].clone().norm_cdf() + &(x[0].clone() * &x[1]).norm_inv_cdf()
+    }
+
+    #[test]
+    fn test_special_functions() {
+        assert!((Erf::erf(0.5_f64) - 0.5204998778130465).abs() < 1e-16);
+        assert!((Erfc::erfc(3.0_f64) / 2.209049699858544e-5 - 1.0).abs() < 1e-15);
+        assert!((1.96_f64.norm_cdf() - 0.9750021048517795).abs() < 1e-16);
+        for p in [1e-300, 1e-10, 0.01, 0.3, 0.5, 0.9, 0.999] {
+            let x = f64::norm_inv_cdf(p);
+            assert!((x.norm_cdf() / p - 1.0).abs() < 1e-12, "{}", p);
         }
+
+        assert_gradients(&[0.4, 0.7], g, g);
+
+        // derivatives are held by single nodes
+        let x = VarGroup::new("x").val(0.4).into_expr();
+        let e = x.clone().norm_cdf();
+        assert_eq!(Graph::from(&e).nodes().len(), 2);
+        assert_eq!(e.grads()[&x], 0.4_f64.norm_pdf());
     }
 }
@@ -0,0 +1,28 @@
+use crate::{
+    scalar::{_is_nan, Abs, KinkError, Scalar, Subgradient},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + PartialOrd> Abs for Expr<T> {
+    #[inline]
+    fn abs_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(self.output(), &T::zero())?;
+        let (o, g) = eval(self.output(), policy);
+        Ok(_Unary::create(self, o, g, _UOp::Abs(policy)))
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + PartialOrd>(x: &T, policy: Subgradient) -> (T, T) {
+    if *x < T::zero() {
+        (-x.clone(), -T::one())
+    } else if *x > T::zero() {
+        (x.clone(), T::one())
+    } else if _is_nan(x) {
+        (x.clone(), T::from(f64::NAN))
+    } else {
+        (x.clone(), policy.pick(-T::one(), T::one()))
+    }
+}
@@ -0,0 +1,20 @@
+use crate::{
+    scalar::{Acos, Scalar, Sqrt},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Acos + Sqrt> Acos for Expr<T> {
+    #[inline]
+    fn acos(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Acos)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Acos + Sqrt>(x: &T) -> (T, T) {
+    let g = -T::one() / (T::one() - x.clone() * x).sqrt();
+    (x.clone().acos(), g)
+}
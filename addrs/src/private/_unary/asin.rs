@@ -0,0 +1,20 @@
+use crate::{
+    scalar::{Asin, Scalar, Sqrt},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Asin + Sqrt> Asin for Expr<T> {
+    #[inline]
+    fn asin(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Asin)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Asin + Sqrt>(x: &T) -> (T, T) {
+    let g = T::one() / (T::one() - x.clone() * x).sqrt();
+    (x.clone().asin(), g)
+}
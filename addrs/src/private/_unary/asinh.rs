@@ -0,0 +1,20 @@
+use crate::{
+    scalar::{Asinh, Scalar, Sqrt},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Asinh + Sqrt> Asinh for Expr<T> {
+    #[inline]
+    fn asinh(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Asinh)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Asinh + Sqrt>(x: &T) -> (T, T) {
+    let g = T::one() / (x.clone() * x + T::one()).sqrt();
+    (x.clone().asinh(), g)
+}
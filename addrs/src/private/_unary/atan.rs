@@ -0,0 +1,20 @@
+use crate::{
+    scalar::{Atan, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Atan> Atan for Expr<T> {
+    #[inline]
+    fn atan(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Atan)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Atan>(x: &T) -> (T, T) {
+    let g = T::one() / (T::one() + x.clone() * x);
+    (x.clone().atan(), g)
+}
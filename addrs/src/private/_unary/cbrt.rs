@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{Cbrt, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Cbrt> Cbrt for Expr<T> {
+    #[inline]
+    fn cbrt(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Cbrt)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Cbrt>(x: &T) -> (T, T) {
+    let o = x.clone().cbrt();
+    let g = T::one() / (o.clone() * &o * T::from(3.0));
+    (o, g)
+}
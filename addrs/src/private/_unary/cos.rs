@@ -0,0 +1,19 @@
+use crate::{
+    scalar::{Cos, Scalar, Sin},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Sin + Cos> Cos for Expr<T> {
+    #[inline]
+    fn cos(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Cos)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Sin + Cos>(x: &T) -> (T, T) {
+    (x.clone().cos(), -x.clone().sin())
+}
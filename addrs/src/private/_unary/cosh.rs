@@ -0,0 +1,19 @@
+use crate::{
+    scalar::{Cosh, Scalar, Sinh},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Cosh + Sinh> Cosh for Expr<T> {
+    #[inline]
+    fn cosh(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Cosh)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Cosh + Sinh>(x: &T) -> (T, T) {
+    (x.clone().cosh(), x.clone().sinh())
+}
@@ -0,0 +1,12 @@
+use crate::{custom::CustomUnary, Expr};
+
+use super::{_UOp, _Unary};
+
+impl<T: Clone> CustomUnary<T> {
+    /// Applies the operation to `x` as a single node.
+    #[inline]
+    pub fn apply(&self, x: Expr<T>) -> Expr<T> {
+        let (o, g) = self.eval(x.output());
+        _Unary::create(x, o, g, _UOp::Custom(self.clone()))
+    }
+}
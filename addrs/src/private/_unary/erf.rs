@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{Erf, Exp, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Erf + Exp> Erf for Expr<T> {
+    #[inline]
+    fn erf(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Erf)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Erf + Exp>(x: &T) -> (T, T) {
+    let o = x.clone().erf();
+    let g = T::from(std::f64::consts::FRAC_2_SQRT_PI) * (-(x.clone() * x)).exp();
+    (o, g)
+}
@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{Erfc, Exp, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Erfc + Exp> Erfc for Expr<T> {
+    #[inline]
+    fn erfc(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Erfc)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Erfc + Exp>(x: &T) -> (T, T) {
+    let o = x.clone().erfc();
+    let g = T::from(-std::f64::consts::FRAC_2_SQRT_PI) * (-(x.clone() * x)).exp();
+    (o, g)
+}
@@ -8,8 +8,13 @@ use super::{_UOp, _Unary};
 impl<T: Scalar + Exp> Exp for Expr<T> {
     #[inline]
     fn exp(self) -> Self {
-        let o = self.output().clone().exp();
-        let g = o.clone();
+        let (o, g) = eval(self.output());
         _Unary::create(self, o, g, _UOp::Exp)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar + Exp>(x: &T) -> (T, T) {
+    let o = x.clone().exp();
+    (o.clone(), o)
+}
@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{Exp2, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Exp2> Exp2 for Expr<T> {
+    #[inline]
+    fn exp2(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Exp2)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Exp2>(x: &T) -> (T, T) {
+    let o = x.clone().exp2();
+    let g = o.clone() * T::from(std::f64::consts::LN_2);
+    (o, g)
+}
@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{ExpM1, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + ExpM1> ExpM1 for Expr<T> {
+    #[inline]
+    fn exp_m1(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::ExpM1)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + ExpM1>(x: &T) -> (T, T) {
+    let o = x.clone().exp_m1();
+    let g = o.clone() + T::one();
+    (o, g)
+}
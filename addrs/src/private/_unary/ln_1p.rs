@@ -0,0 +1,19 @@
+use crate::{
+    scalar::{Ln1p, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Ln1p> Ln1p for Expr<T> {
+    #[inline]
+    fn ln_1p(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Ln1p)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Ln1p>(x: &T) -> (T, T) {
+    (x.clone().ln_1p(), T::one() / (T::one() + x))
+}
@@ -8,8 +8,12 @@ use super::{_UOp, _Unary};
 impl<T: Scalar + Log> Log for Expr<T> {
     #[inline]
     fn log(self) -> Self {
-        let o = self.output().clone().log();
-        let g = T::one() / self.output();
+        let (o, g) = eval(self.output());
         _Unary::create(self, o, g, _UOp::Log)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar + Log>(x: &T) -> (T, T) {
+    (x.clone().log(), T::one() / x)
+}
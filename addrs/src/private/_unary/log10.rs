@@ -0,0 +1,20 @@
+use crate::{
+    scalar::{Log10, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Log10> Log10 for Expr<T> {
+    #[inline]
+    fn log10(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Log10)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Log10>(x: &T) -> (T, T) {
+    let g = T::one() / (x.clone() * T::from(std::f64::consts::LN_10));
+    (x.clone().log10(), g)
+}
@@ -0,0 +1,20 @@
+use crate::{
+    scalar::{Log2, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Log2> Log2 for Expr<T> {
+    #[inline]
+    fn log2(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Log2)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Log2>(x: &T) -> (T, T) {
+    let g = T::one() / (x.clone() * T::from(std::f64::consts::LN_2));
+    (x.clone().log2(), g)
+}
@@ -8,8 +8,12 @@ impl<T: Scalar> Neg for Expr<T> {
     type Output = Self;
     #[inline]
     fn neg(self) -> Self::Output {
-        let o = -self.output().clone();
-        let g = -T::one();
+        let (o, g) = eval(self.output());
         _Unary::create(self, o, g, _UOp::Neg)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar>(x: &T) -> (T, T) {
+    (-x.clone(), -T::one())
+}
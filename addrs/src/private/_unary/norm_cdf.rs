@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{NormCdf, NormPdf, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + NormCdf + NormPdf> NormCdf for Expr<T> {
+    #[inline]
+    fn norm_cdf(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::NormCdf)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + NormCdf + NormPdf>(x: &T) -> (T, T) {
+    let o = x.clone().norm_cdf();
+    let g = x.clone().norm_pdf();
+    (o, g)
+}
@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{NormInvCdf, NormPdf, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + NormInvCdf + NormPdf> NormInvCdf for Expr<T> {
+    #[inline]
+    fn norm_inv_cdf(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::NormInvCdf)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + NormInvCdf + NormPdf>(x: &T) -> (T, T) {
+    let o = x.clone().norm_inv_cdf();
+    let g = T::one() / o.clone().norm_pdf();
+    (o, g)
+}
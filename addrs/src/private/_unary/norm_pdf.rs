@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{NormPdf, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + NormPdf> NormPdf for Expr<T> {
+    #[inline]
+    fn norm_pdf(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::NormPdf)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + NormPdf>(x: &T) -> (T, T) {
+    let o = x.clone().norm_pdf();
+    let g = -(x.clone() * &o);
+    (o, g)
+}
@@ -0,0 +1,24 @@
+use crate::{
+    scalar::{Powi, Scalar},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Powi> Powi for Expr<T> {
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        let (o, g) = eval(self.output(), n);
+        _Unary::create(self, o, g, _UOp::Powi(n))
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Powi>(x: &T, n: i32) -> (T, T) {
+    let o = x.clone().powi(n);
+    let g = match n {
+        0 => T::zero(),
+        _ => T::from(n as f64) * x.clone().powi(n - 1),
+    };
+    (o, g)
+}
@@ -0,0 +1,28 @@
+use crate::{
+    scalar::{_is_nan, KinkError, Relu, Scalar, Subgradient},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + PartialOrd> Relu for Expr<T> {
+    #[inline]
+    fn relu_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(self.output(), &T::zero())?;
+        let (o, g) = eval(self.output(), policy);
+        Ok(_Unary::create(self, o, g, _UOp::Relu(policy)))
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + PartialOrd>(x: &T, policy: Subgradient) -> (T, T) {
+    if *x < T::zero() {
+        (T::zero(), T::zero())
+    } else if *x > T::zero() {
+        (x.clone(), T::one())
+    } else if _is_nan(x) {
+        (x.clone(), T::from(f64::NAN))
+    } else {
+        (x.clone(), policy.pick(T::zero(), T::one()))
+    }
+}
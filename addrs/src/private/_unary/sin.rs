@@ -0,0 +1,19 @@
+use crate::{
+    scalar::{Cos, Scalar, Sin},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Sin + Cos> Sin for Expr<T> {
+    #[inline]
+    fn sin(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Sin)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Sin + Cos>(x: &T) -> (T, T) {
+    (x.clone().sin(), x.clone().cos())
+}
@@ -0,0 +1,19 @@
+use crate::{
+    scalar::{Cosh, Scalar, Sinh},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Sinh + Cosh> Sinh for Expr<T> {
+    #[inline]
+    fn sinh(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Sinh)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Sinh + Cosh>(x: &T) -> (T, T) {
+    (x.clone().sinh(), x.clone().cosh())
+}
@@ -8,8 +8,14 @@ use super::{_UOp, _Unary};
 impl<T: Scalar + Sqrt> Sqrt for Expr<T> {
     #[inline]
     fn sqrt(self) -> Self {
-        let o = self.output().clone().sqrt();
-        let g = T::from(0.5_f64) / &o;
+        let (o, g) = eval(self.output());
         _Unary::create(self, o, g, _UOp::Sqrt)
     }
 }
+
+#[inline]
+pub(super) fn eval<T: Scalar + Sqrt>(x: &T) -> (T, T) {
+    let o = x.clone().sqrt();
+    let g = T::from(0.5_f64) / &o;
+    (o, g)
+}
@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{Scalar, Tan},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Tan> Tan for Expr<T> {
+    #[inline]
+    fn tan(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Tan)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Tan>(x: &T) -> (T, T) {
+    let o = x.clone().tan();
+    let g = T::one() + o.clone() * &o;
+    (o, g)
+}
@@ -0,0 +1,21 @@
+use crate::{
+    scalar::{Scalar, Tanh},
+    Expr,
+};
+
+use super::{_UOp, _Unary};
+
+impl<T: Scalar + Tanh> Tanh for Expr<T> {
+    #[inline]
+    fn tanh(self) -> Self {
+        let (o, g) = eval(self.output());
+        _Unary::create(self, o, g, _UOp::Tanh)
+    }
+}
+
+#[inline]
+pub(super) fn eval<T: Scalar + Tanh>(x: &T) -> (T, T) {
+    let o = x.clone().tanh();
+    let g = T::one() - o.clone() * &o;
+    (o, g)
+}
@@ -0,0 +1,146 @@
+use crate::{
+    private::_Inst,
+    scalar::{Elementary, Scalar},
+    Expr, Graph, Id,
+};
+
+/// Expression compiled into a flat list of instructions.
+///
+/// Inputs are given as a slice of values in the order of [`ids`](Self::ids),
+/// and the function and its gradient are evaluated without building any graph.
+/// Buffers are allocated once when compiled, so that evaluations do not allocate.
+///
+/// Compressed expressions are evaluated as constants with fixed derivatives
+/// since the graphs behind them are no longer available.
+#[derive(Debug, Clone)]
+pub struct Program<T> {
+    ids: Vec<Id>,
+    insts: Vec<_Inst<T>>,
+    output: usize,
+    regs: Vec<T>,
+    partials: Vec<(T, T)>,
+    adjoints: Vec<T>,
+}
+
+impl<T: Scalar> Program<T> {
+    pub fn new(expr: &Expr<T>) -> Self {
+        let graph = Graph::from(expr);
+        let (ids, insts, regs) = _Inst::compile(&graph);
+        let n = insts.len();
+        Self {
+            ids,
+            insts,
+            output: graph.outputs()[0],
+            regs,
+            partials: vec![(T::zero(), T::zero()); n],
+            adjoints: vec![T::zero(); n],
+        }
+    }
+}
+
+impl<T> Program<T> {
+    /// Variables corresponding to inputs, sorted by [`Id`].
+    #[inline]
+    pub fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+    /// Number of instructions.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.insts.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.insts.is_empty()
+    }
+}
+
+impl<T: Elementary> Program<T> {
+    /// Evaluates the function at `inputs`.
+    ///
+    /// # Panics
+    /// Panics if the length of `inputs` differs from that of [`ids`](Self::ids).
+    pub fn eval(&mut self, inputs: &[T]) -> T {
+        assert_eq!(inputs.len(), self.ids.len(), "number of inputs mismatch");
+        for k in 0..=self.output {
+            let (regs, rest) = self.regs.split_at_mut(k);
+            if let Some((o, gl, gr)) = self.insts[k].forward(inputs, regs) {
+                rest[0] = o;
+                self.partials[k] = (gl, gr);
+            }
+        }
+        self.regs[self.output].clone()
+    }
+
+    /// Evaluates the function at `inputs` and writes its gradient into `grad`.
+    ///
+    /// # Panics
+    /// Panics if the length of `inputs` or `grad` differs from that of [`ids`](Self::ids).
+    pub fn eval_grad(&mut self, inputs: &[T], grad: &mut [T]) -> T {
+        assert_eq!(grad.len(), self.ids.len(), "length of gradient mismatch");
+        let res = self.eval(inputs);
+        grad.iter_mut().for_each(|g| *g = T::zero());
+        self.adjoints.iter_mut().for_each(|a| *a = T::zero());
+        self.adjoints[self.output] = T::one();
+        for k in (0..=self.output).rev() {
+            let (adjoints, rest) = self.adjoints.split_at_mut(k);
+            if rest[0].is_zero() {
+                continue;
+            }
+            let (gl, gr) = &self.partials[k];
+            self.insts[k].backward(&rest[0], gl, gr, adjoints, grad);
+        }
+        res
+    }
+}
+
+impl<T: Scalar> Expr<T> {
+    /// Compiles the expression into a reusable [`Program`].
+    #[inline]
+    pub fn compile(&self) -> Program<T> {
+        Program::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{Exp, Log, Sqrt},
+        VarGroup,
+    };
+
+    use super::*;
+
+    fn payoff(x: &[Expr<f64>]) -> Expr<f64> {
+        let fwd = x[0].clone() * (x[1].clone() * &x[3]).exp();
+        let vol = x[2].clone() * x[3].clone().sqrt();
+        let d = ((fwd.clone() / 100.0).log() + vol.clone() * &vol * 0.5) / &vol;
+        let e = d.exp();
+        (fwd - 100.0) * &e / (e + 1.0)
+    }
+
+    #[test]
+    fn test_program() {
+        let grp = VarGroup::new("x");
+        let x: Vec<_> = [100.0, 0.01, 0.2, 1.0]
+            .map(|v| grp.val(v).into_expr())
+            .into();
+        let mut program = payoff(&x).compile();
+        assert_eq!(program.ids().len(), 4);
+
+        let mut grad = vec![0.0; 4];
+        for vals in [
+            [100.0, 0.01, 0.2, 1.0],
+            [95.0, 0.02, 0.3, 0.5],
+            [120.0, -0.01, 0.1, 2.0],
+        ] {
+            let x: Vec<_> = vals.map(|v| grp.val(v).into_expr()).into();
+            let expected = payoff(&x);
+            assert_eq!(program.eval(&vals), *expected.output());
+            assert_eq!(program.eval_grad(&vals, &mut grad), *expected.output());
+            for (actual, expected) in grad.iter().zip(expected.grads().values()) {
+                assert!((actual - expected).abs() < 1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    graph::{GraphError, Node},
+    scalar::Elementary,
+    Expr, Gradients, Graph, Id, VarGroup,
+};
+
+/// Expressions evaluated again with new values of variables.
+///
+/// Values of variables are updated by [`set`](Self::set) or [`set_group`](Self::set_group),
+/// and [`forward`](Self::forward) refreshes outputs and partial derivatives of all nodes in place.
+/// None of them allocates, so that a fixed graph can be reused for many evaluations.
+///
+/// Compressed nodes keep their values since the graphs behind them are no longer available.
+#[derive(Debug, Clone)]
+pub struct Replay<T> {
+    graph: Graph<T>,
+    /// keyed by the group and the number of a variable
+    vars: HashMap<(usize, usize), usize>,
+    adjoints: Vec<Option<T>>,
+}
+
+impl<T: Clone> Replay<T> {
+    pub fn new(exprs: &[Expr<T>]) -> Self {
+        Self::from_graph(Graph::new(exprs))
+    }
+}
+
+impl<T> Replay<T> {
+    fn from_graph(graph: Graph<T>) -> Self {
+        let mut vars = HashMap::new();
+        for (i, n) in graph.nodes().iter().enumerate() {
+            if let Node::Var { id, .. } = n {
+                vars.insert((id.group, id.num), i);
+            }
+        }
+        let mut adjoints = Vec::new();
+        adjoints.resize_with(graph.nodes().len(), || None);
+        Self {
+            graph,
+            vars,
+            adjoints,
+        }
+    }
+
+    #[inline]
+    pub fn graph(&self) -> &Graph<T> {
+        &self.graph
+    }
+    #[inline]
+    pub fn into_graph(self) -> Graph<T> {
+        self.graph
+    }
+    /// Returns the current value of the `k`-th expression.
+    #[inline]
+    pub fn output(&self, k: usize) -> &T {
+        self.graph.nodes()[self.graph.outputs()[k]].output()
+    }
+    #[inline]
+    pub fn outputs(&self) -> impl Iterator<Item = &T> {
+        let nodes = self.graph.nodes();
+        self.graph.outputs().iter().map(|i| nodes[*i].output())
+    }
+
+    /// Updates the value of a variable.
+    /// Returns `false` if none of the expressions depends on it.
+    ///
+    /// Outputs are not refreshed until [`forward`](Self::forward) is called.
+    pub fn set(&mut self, id: &Id, val: T) -> bool {
+        self._set(id.group, id.num, val)
+    }
+    /// Updates values of variables in `grp` in the order of their creation.
+    /// Variables on which none of the expressions depends are skipped.
+    pub fn set_group(&mut self, grp: &VarGroup, vals: impl IntoIterator<Item = T>) {
+        for (num, val) in vals.into_iter().enumerate() {
+            self._set(grp.id(), num, val);
+        }
+    }
+    #[inline]
+    fn _set(&mut self, group: usize, num: usize, val: T) -> bool {
+        let Some(i) = self.vars.get(&(group, num)) else {
+            return false;
+        };
+        let Node::Var { o, .. } = &mut self.graph.nodes_mut()[*i] else {
+            unreachable!()
+        };
+        *o = val;
+        true
+    }
+}
+
+impl<T: Elementary> Replay<T> {
+    /// Evaluates all nodes again with current values of variables.
+    pub fn forward(&mut self) {
+        let nodes = self.graph.nodes_mut();
+        for k in 0..nodes.len() {
+            let (inputs, rest) = nodes.split_at_mut(k);
+            match &mut rest[0] {
+                Node::Unary { op, i, o, g } => (*o, *g) = op.eval(inputs[*i].output()),
+                Node::CustomUnary { op, i, o, g } => (*o, *g) = op.eval(inputs[*i].output()),
+                Node::CustomBinary {
+                    op,
+                    l,
+                    r,
+                    o,
+                    gl,
+                    gr,
+                } => (*o, *gl, *gr) = op.eval(inputs[*l].output(), inputs[*r].output()),
+                Node::Binary {
+                    op,
+                    l,
+                    r,
+                    o,
+                    gl,
+                    gr,
+                } => (*o, *gl, *gr) = op.eval(inputs[*l].output(), inputs[*r].output()),
+                Node::Select {
+                    cmp,
+                    x,
+                    y,
+                    a,
+                    b,
+                    o,
+                    cond,
+                } => {
+                    *cond = cmp.eval(inputs[*x].output(), inputs[*y].output());
+                    *o = inputs[if *cond { *a } else { *b }].output().clone();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Calculates derivatives of the `k`-th expression with a linear reverse scan.
+    pub fn grads(&mut self, k: usize) -> Gradients<T> {
+        let nodes = self.graph.nodes();
+        let root = self.graph.outputs()[k];
+        let adjoints = &mut self.adjoints;
+        let acc = |adjoints: &mut [Option<T>], j: usize, g: T| match &mut adjoints[j] {
+            Some(x) => *x += &g,
+            x => *x = Some(g),
+        };
+        acc(adjoints, root, T::one());
+
+        let mut res = BTreeMap::new();
+        let mut merge = |id: &Id, g: T| match res.get_mut(id) {
+            Some(x) => *x += &g,
+            None => {
+                res.insert(id.clone(), g);
+            }
+        };
+        for (i, n) in nodes[..=root].iter().enumerate().rev() {
+            let Some(adj) = adjoints[i].take() else {
+                continue;
+            };
+            match n {
+                Node::Var { id, .. } => merge(id, adj),
+                Node::Const { .. } => {}
+                Node::Unary { i, g, .. } | Node::CustomUnary { i, g, .. } => {
+                    acc(adjoints, *i, adj * g)
+                }
+                Node::Binary { l, r, gl, gr, .. } | Node::CustomBinary { l, r, gl, gr, .. } => {
+                    acc(adjoints, *l, adj.clone() * gl);
+                    acc(adjoints, *r, adj * gr);
+                }
+                Node::Compressed { gs, .. } => {
+                    for (id, g) in gs {
+                        merge(id, adj.clone() * g);
+                    }
+                }
+                Node::Select { a, b, cond, .. } => acc(adjoints, if *cond { *a } else { *b }, adj),
+            }
+        }
+        res.into()
+    }
+}
+
+impl<T> TryFrom<Graph<T>> for Replay<T> {
+    type Error = GraphError;
+    #[inline]
+    fn try_from(graph: Graph<T>) -> Result<Self, Self::Error> {
+        graph.check()?;
+        Ok(Self::from_graph(graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{Exp, Log, Sqrt},
+        Tape,
+    };
+
+    use super::*;
+
+    fn price(x: &[Expr<f64>]) -> Expr<f64> {
+        let df = (-x[0].clone() * &x[1]).exp();
+        (df.clone() * &x[2] + x[2].clone().sqrt() / &df - x[1].clone().log()) * 2.0 - 1.0
+    }
+
+    #[test]
+    fn test_replay() {
+        let grp = VarGroup::new("x");
+        let vars: Vec<_> = [0.3, 1.2, 2.5].map(|v| grp.val(v).into_expr()).into();
+        let tape = Tape::new();
+        let taped: Vec<_> = vars
+            .iter()
+            .map(|x| tape.var(x.as_var().unwrap().clone()))
+            .collect();
+        let mut replay = Replay::new(&[price(&vars), price(&taped)]);
+
+        for vals in [[0.4, 1.1, 2.7], [0.1, 0.5, 3.0]] {
+            replay.set_group(&grp, vals);
+            replay.forward();
+
+            let grp = VarGroup::new("y");
+            let vars: Vec<_> = vals.map(|v| grp.val(v).into_expr()).into();
+            let expected = price(&vars);
+            for k in 0..2 {
+                assert_eq!(replay.output(k), expected.output());
+                // variables are numbered in the same order in both groups
+                let (actual, expected) = (replay.grads(k), expected.grads());
+                assert_eq!(actual.len(), expected.len());
+                for (actual, expected) in actual.values().zip(expected.values()) {
+                    assert!((actual - expected).abs() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay_set() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(1.0).into_expr();
+        let unused = grp.val(2.0);
+        let mut replay = Replay::new(&[x.clone().exp()]);
+        assert!(replay.set(x.as_var().unwrap().id(), 0.0));
+        assert!(!replay.set(unused.id(), 0.0));
+        assert_eq!(*replay.output(0), 1.0_f64.exp());
+        replay.forward();
+        assert_eq!(*replay.output(0), 1.0);
+    }
+}
@@ -1,7 +1,12 @@
+mod dual;
+mod special;
+
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use num_traits::{One, Zero};
 
+pub use dual::Dual;
+
 pub trait Scalar:
     Clone
     + Zero
@@ -85,3 +90,589 @@ impl Log for f32 {
         f32::ln(self)
     }
 }
+
+pub trait Erf {
+    fn erf(self) -> Self;
+}
+impl Erf for f64 {
+    fn erf(self) -> Self {
+        special::erf(self)
+    }
+}
+impl Erf for f32 {
+    fn erf(self) -> Self {
+        special::erf(self as f64) as f32
+    }
+}
+
+pub trait Erfc {
+    fn erfc(self) -> Self;
+}
+impl Erfc for f64 {
+    fn erfc(self) -> Self {
+        special::erfc(self)
+    }
+}
+impl Erfc for f32 {
+    fn erfc(self) -> Self {
+        special::erfc(self as f64) as f32
+    }
+}
+
+/// Density of the standard normal distribution.
+pub trait NormPdf {
+    fn norm_pdf(self) -> Self;
+}
+impl NormPdf for f64 {
+    fn norm_pdf(self) -> Self {
+        special::norm_pdf(self)
+    }
+}
+impl NormPdf for f32 {
+    fn norm_pdf(self) -> Self {
+        special::norm_pdf(self as f64) as f32
+    }
+}
+
+/// Cumulative distribution function of the standard normal distribution.
+pub trait NormCdf {
+    fn norm_cdf(self) -> Self;
+}
+impl NormCdf for f64 {
+    fn norm_cdf(self) -> Self {
+        special::norm_cdf(self)
+    }
+}
+impl NormCdf for f32 {
+    fn norm_cdf(self) -> Self {
+        special::norm_cdf(self as f64) as f32
+    }
+}
+
+/// Inverse of [`NormCdf`], i.e. quantiles of the standard normal distribution.
+pub trait NormInvCdf {
+    fn norm_inv_cdf(self) -> Self;
+}
+impl NormInvCdf for f64 {
+    fn norm_inv_cdf(self) -> Self {
+        special::norm_inv_cdf(self)
+    }
+}
+impl NormInvCdf for f32 {
+    fn norm_inv_cdf(self) -> Self {
+        special::norm_inv_cdf(self as f64) as f32
+    }
+}
+
+pub trait Powi {
+    fn powi(self, n: i32) -> Self;
+}
+impl Powi for f64 {
+    fn powi(self, n: i32) -> Self {
+        f64::powi(self, n)
+    }
+}
+impl Powi for f32 {
+    fn powi(self, n: i32) -> Self {
+        f32::powi(self, n)
+    }
+}
+
+pub trait Powf<Rhs = Self> {
+    fn powf(self, n: Rhs) -> Self;
+}
+impl Powf for f64 {
+    fn powf(self, n: Self) -> Self {
+        f64::powf(self, n)
+    }
+}
+impl Powf for f32 {
+    fn powf(self, n: Self) -> Self {
+        f32::powf(self, n)
+    }
+}
+
+pub trait Sin {
+    fn sin(self) -> Self;
+}
+impl Sin for f64 {
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+}
+impl Sin for f32 {
+    fn sin(self) -> Self {
+        f32::sin(self)
+    }
+}
+
+pub trait Cos {
+    fn cos(self) -> Self;
+}
+impl Cos for f64 {
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+}
+impl Cos for f32 {
+    fn cos(self) -> Self {
+        f32::cos(self)
+    }
+}
+
+pub trait Tan {
+    fn tan(self) -> Self;
+}
+impl Tan for f64 {
+    fn tan(self) -> Self {
+        f64::tan(self)
+    }
+}
+impl Tan for f32 {
+    fn tan(self) -> Self {
+        f32::tan(self)
+    }
+}
+
+pub trait Asin {
+    fn asin(self) -> Self;
+}
+impl Asin for f64 {
+    fn asin(self) -> Self {
+        f64::asin(self)
+    }
+}
+impl Asin for f32 {
+    fn asin(self) -> Self {
+        f32::asin(self)
+    }
+}
+
+pub trait Acos {
+    fn acos(self) -> Self;
+}
+impl Acos for f64 {
+    fn acos(self) -> Self {
+        f64::acos(self)
+    }
+}
+impl Acos for f32 {
+    fn acos(self) -> Self {
+        f32::acos(self)
+    }
+}
+
+pub trait Atan {
+    fn atan(self) -> Self;
+}
+impl Atan for f64 {
+    fn atan(self) -> Self {
+        f64::atan(self)
+    }
+}
+impl Atan for f32 {
+    fn atan(self) -> Self {
+        f32::atan(self)
+    }
+}
+
+pub trait Atan2 {
+    /// Four-quadrant arctangent of `self / x`.
+    fn atan2(self, x: Self) -> Self;
+}
+impl Atan2 for f64 {
+    fn atan2(self, x: Self) -> Self {
+        f64::atan2(self, x)
+    }
+}
+impl Atan2 for f32 {
+    fn atan2(self, x: Self) -> Self {
+        f32::atan2(self, x)
+    }
+}
+
+pub trait Tanh {
+    fn tanh(self) -> Self;
+}
+impl Tanh for f64 {
+    fn tanh(self) -> Self {
+        f64::tanh(self)
+    }
+}
+impl Tanh for f32 {
+    fn tanh(self) -> Self {
+        f32::tanh(self)
+    }
+}
+
+pub trait Sinh {
+    fn sinh(self) -> Self;
+}
+impl Sinh for f64 {
+    fn sinh(self) -> Self {
+        f64::sinh(self)
+    }
+}
+impl Sinh for f32 {
+    fn sinh(self) -> Self {
+        f32::sinh(self)
+    }
+}
+
+pub trait Cosh {
+    fn cosh(self) -> Self;
+}
+impl Cosh for f64 {
+    fn cosh(self) -> Self {
+        f64::cosh(self)
+    }
+}
+impl Cosh for f32 {
+    fn cosh(self) -> Self {
+        f32::cosh(self)
+    }
+}
+
+pub trait Asinh {
+    fn asinh(self) -> Self;
+}
+impl Asinh for f64 {
+    fn asinh(self) -> Self {
+        f64::asinh(self)
+    }
+}
+impl Asinh for f32 {
+    fn asinh(self) -> Self {
+        f32::asinh(self)
+    }
+}
+
+pub trait Ln1p {
+    fn ln_1p(self) -> Self;
+}
+impl Ln1p for f64 {
+    fn ln_1p(self) -> Self {
+        f64::ln_1p(self)
+    }
+}
+impl Ln1p for f32 {
+    fn ln_1p(self) -> Self {
+        f32::ln_1p(self)
+    }
+}
+
+pub trait ExpM1 {
+    fn exp_m1(self) -> Self;
+}
+impl ExpM1 for f64 {
+    fn exp_m1(self) -> Self {
+        f64::exp_m1(self)
+    }
+}
+impl ExpM1 for f32 {
+    fn exp_m1(self) -> Self {
+        f32::exp_m1(self)
+    }
+}
+
+pub trait Log2 {
+    fn log2(self) -> Self;
+}
+impl Log2 for f64 {
+    fn log2(self) -> Self {
+        f64::log2(self)
+    }
+}
+impl Log2 for f32 {
+    fn log2(self) -> Self {
+        f32::log2(self)
+    }
+}
+
+pub trait Log10 {
+    fn log10(self) -> Self;
+}
+impl Log10 for f64 {
+    fn log10(self) -> Self {
+        f64::log10(self)
+    }
+}
+impl Log10 for f32 {
+    fn log10(self) -> Self {
+        f32::log10(self)
+    }
+}
+
+pub trait Exp2 {
+    fn exp2(self) -> Self;
+}
+impl Exp2 for f64 {
+    fn exp2(self) -> Self {
+        f64::exp2(self)
+    }
+}
+impl Exp2 for f32 {
+    fn exp2(self) -> Self {
+        f32::exp2(self)
+    }
+}
+
+pub trait Cbrt {
+    fn cbrt(self) -> Self;
+}
+impl Cbrt for f64 {
+    fn cbrt(self) -> Self {
+        f64::cbrt(self)
+    }
+}
+impl Cbrt for f32 {
+    fn cbrt(self) -> Self {
+        f32::cbrt(self)
+    }
+}
+
+/// Policy of derivatives at kinks of non-smooth operations.
+///
+/// For binary operations, one-sided limits are taken along `l - r`
+/// and partial derivatives sum to one at a tie,
+/// e.g. those of `max(l, r)` at `l == r` are `(0, 1)` for `Left` and `(1, 0)` for `Right`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Subgradient {
+    /// One-sided derivative from below.
+    Left,
+    /// One-sided derivative from above.
+    Right,
+    /// Mean of the one-sided derivatives.
+    #[default]
+    Average,
+    /// Operations at kinks or with NaN operands fail with [`KinkError`].
+    /// When evaluated again, e.g. by [`crate::Replay`], derivatives at kinks are NaN.
+    Error,
+}
+
+impl Subgradient {
+    /// Derivative at a kink from one-sided derivatives.
+    #[inline]
+    pub(crate) fn pick<T: Scalar>(self, left: T, right: T) -> T {
+        match self {
+            Self::Left => left,
+            Self::Right => right,
+            Self::Average => (left + right) * T::from(0.5),
+            Self::Error => T::from(f64::NAN),
+        }
+    }
+    /// Fails under [`Subgradient::Error`] unless `x` and `y` are strictly ordered,
+    /// i.e. they are equal or either is NaN.
+    #[inline]
+    pub(crate) fn check<T: PartialOrd>(self, x: &T, y: &T) -> Result<(), KinkError> {
+        use std::cmp::Ordering::{Greater, Less};
+        let ordered = matches!(x.partial_cmp(y), Some(Less | Greater));
+        match self == Self::Error && !ordered {
+            true => Err(KinkError),
+            false => Ok(()),
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn _is_nan<T: PartialOrd>(x: &T) -> bool {
+    x.partial_cmp(x).is_none()
+}
+
+/// Maximum propagating NaN unlike [`f64::max`].
+#[inline]
+fn _max<T: PartialOrd>(l: T, r: T) -> T {
+    match l.partial_cmp(&r) {
+        Some(std::cmp::Ordering::Less) => r,
+        None if _is_nan(&r) => r,
+        _ => l,
+    }
+}
+
+/// Minimum propagating NaN unlike [`f64::min`].
+#[inline]
+fn _min<T: PartialOrd>(l: T, r: T) -> T {
+    match l.partial_cmp(&r) {
+        Some(std::cmp::Ordering::Greater) => r,
+        None if _is_nan(&r) => r,
+        _ => l,
+    }
+}
+
+/// Non-smooth operation evaluated at its kink under [`Subgradient::Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KinkError;
+
+impl std::fmt::Display for KinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "derivative is undefined at a kink")
+    }
+}
+
+impl std::error::Error for KinkError {}
+
+pub trait Abs: Sized {
+    fn abs_with(self, policy: Subgradient) -> Result<Self, KinkError>;
+    #[inline]
+    fn abs(self) -> Self {
+        self.abs_with(Subgradient::Average).unwrap()
+    }
+}
+impl Abs for f64 {
+    fn abs_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &0.0)?;
+        Ok(f64::abs(self))
+    }
+}
+impl Abs for f32 {
+    fn abs_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &0.0)?;
+        Ok(f32::abs(self))
+    }
+}
+
+/// `max(x, 0)`.
+pub trait Relu: Sized {
+    fn relu_with(self, policy: Subgradient) -> Result<Self, KinkError>;
+    #[inline]
+    fn relu(self) -> Self {
+        self.relu_with(Subgradient::Average).unwrap()
+    }
+}
+impl Relu for f64 {
+    fn relu_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &0.0)?;
+        Ok(_max(self, 0.0))
+    }
+}
+impl Relu for f32 {
+    fn relu_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &0.0)?;
+        Ok(_max(self, 0.0))
+    }
+}
+
+pub trait Max<Rhs = Self>: Sized {
+    fn max_with(self, rhs: Rhs, policy: Subgradient) -> Result<Self, KinkError>;
+    #[inline]
+    fn max(self, rhs: Rhs) -> Self {
+        self.max_with(rhs, Subgradient::Average).unwrap()
+    }
+}
+impl Max for f64 {
+    fn max_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &rhs)?;
+        Ok(_max(self, rhs))
+    }
+}
+impl Max for f32 {
+    fn max_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &rhs)?;
+        Ok(_max(self, rhs))
+    }
+}
+
+pub trait Min<Rhs = Self>: Sized {
+    fn min_with(self, rhs: Rhs, policy: Subgradient) -> Result<Self, KinkError>;
+    #[inline]
+    fn min(self, rhs: Rhs) -> Self {
+        self.min_with(rhs, Subgradient::Average).unwrap()
+    }
+}
+impl Min for f64 {
+    fn min_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &rhs)?;
+        Ok(_min(self, rhs))
+    }
+}
+impl Min for f32 {
+    fn min_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self, &rhs)?;
+        Ok(_min(self, rhs))
+    }
+}
+
+/// `min(max(x, lo), hi)`.
+pub trait Clamp<Rhs = Self>: Sized {
+    fn clamp_with(self, lo: Rhs, hi: Rhs, policy: Subgradient) -> Result<Self, KinkError>;
+    #[inline]
+    fn clamp(self, lo: Rhs, hi: Rhs) -> Self {
+        self.clamp_with(lo, hi, Subgradient::Average).unwrap()
+    }
+}
+impl<S: Max<R> + Min<R>, R> Clamp<R> for S {
+    #[inline]
+    fn clamp_with(self, lo: R, hi: R, policy: Subgradient) -> Result<Self, KinkError> {
+        self.max_with(lo, policy)?.min_with(hi, policy)
+    }
+}
+
+/// Scalars supporting all operations of expressions.
+///
+/// Required to evaluate recorded operations again.
+pub trait Elementary:
+    Scalar
+    + PartialOrd
+    + Sqrt
+    + Exp
+    + Log
+    + Powi
+    + Powf
+    + Sin
+    + Cos
+    + Tan
+    + Asin
+    + Acos
+    + Atan
+    + Atan2
+    + Tanh
+    + Sinh
+    + Cosh
+    + Asinh
+    + Ln1p
+    + ExpM1
+    + Log2
+    + Log10
+    + Exp2
+    + Cbrt
+    + Erf
+    + Erfc
+    + NormPdf
+    + NormCdf
+    + NormInvCdf
+{
+}
+
+impl<T> Elementary for T where
+    T: Scalar
+        + PartialOrd
+        + Sqrt
+        + Exp
+        + Log
+        + Powi
+        + Powf
+        + Sin
+        + Cos
+        + Tan
+        + Asin
+        + Acos
+        + Atan
+        + Atan2
+        + Tanh
+        + Sinh
+        + Cosh
+        + Asinh
+        + Ln1p
+        + ExpM1
+        + Log2
+        + Log10
+        + Exp2
+        + Cbrt
+        + Erf
+        + Erfc
+        + NormPdf
+        + NormCdf
+        + NormInvCdf
+{
+}
@@ -0,0 +1,504 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num_traits::{One, Zero};
+
+// `Tan` is not imported so that it does not shadow the accessor of tangents.
+use super::{
+    Abs, Acos, Asin, Asinh, Atan, Atan2, Cbrt, Cos, Cosh, Erf, Erfc, Exp, Exp2, ExpM1, KinkError,
+    Ln1p, Log, Log10, Log2, Max, Min, NormCdf, NormInvCdf, NormPdf, Powf, Powi, Relu, Scalar, Sin,
+    Sinh, Sqrt, Subgradient, Tanh,
+};
+
+/// Dual number for forward mode differentiation.
+///
+/// Holds a value and a tangent, i.e. a directional derivative of the value.
+/// Comparison only looks at values, as [`crate::Expr`] only looks at outputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dual<T> {
+    val: T,
+    tan: T,
+}
+
+impl<T> Dual<T> {
+    #[inline]
+    pub fn new(val: T, tan: T) -> Self {
+        Self { val, tan }
+    }
+    #[inline]
+    pub fn val(&self) -> &T {
+        &self.val
+    }
+    /// With [`Tan`](super::Tan) in scope, call this as `Dual::tan(&x)`.
+    #[inline]
+    pub fn tan(&self) -> &T {
+        &self.tan
+    }
+    #[inline]
+    pub fn into_parts(self) -> (T, T) {
+        (self.val, self.tan)
+    }
+}
+
+impl<T: Zero> Dual<T> {
+    /// Dual number whose tangent is zero.
+    #[inline]
+    pub fn constant(val: T) -> Self {
+        Self::new(val, T::zero())
+    }
+}
+
+impl<T: Scalar> From<f64> for Dual<T> {
+    #[inline]
+    fn from(val: f64) -> Self {
+        Self::constant(val.into())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Dual<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.val.eq(&other.val)
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Dual<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.val.partial_cmp(&other.val)
+    }
+}
+
+impl<T: Scalar> Zero for Dual<T> {
+    #[inline]
+    fn zero() -> Self {
+        Self::constant(T::zero())
+    }
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.val.is_zero() && self.tan.is_zero()
+    }
+}
+
+impl<T: Scalar> One for Dual<T> {
+    #[inline]
+    fn one() -> Self {
+        Self::constant(T::one())
+    }
+}
+
+impl<T: Scalar> Neg for Dual<T> {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Self::new(-self.val, -self.tan)
+    }
+}
+
+impl<T: Scalar> Add<&Self> for Dual<T> {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: &Self) -> Self::Output {
+        Self::new(self.val + &rhs.val, self.tan + &rhs.tan)
+    }
+}
+
+impl<T: Scalar> Sub<&Self> for Dual<T> {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: &Self) -> Self::Output {
+        Self::new(self.val - &rhs.val, self.tan - &rhs.tan)
+    }
+}
+
+impl<T: Scalar> Mul<&Self> for Dual<T> {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: &Self) -> Self::Output {
+        let tan = self.tan * &rhs.val + &(rhs.tan.clone() * &self.val);
+        Self::new(self.val * &rhs.val, tan)
+    }
+}
+
+impl<T: Scalar> Div<&Self> for Dual<T> {
+    type Output = Self;
+    #[inline]
+    fn div(self, rhs: &Self) -> Self::Output {
+        let val = self.val / &rhs.val;
+        let tan = (self.tan - &(rhs.tan.clone() * &val)) / &rhs.val;
+        Self::new(val, tan)
+    }
+}
+
+macro_rules! define_binary_operations {
+    ($trait:ident, $func:ident, $assign_trait:ident, $assign_func:ident) => {
+        impl<T: Scalar> $trait for Dual<T> {
+            type Output = Self;
+            #[inline]
+            fn $func(self, rhs: Self) -> Self::Output {
+                $trait::$func(self, &rhs)
+            }
+        }
+        impl<T: Scalar> $assign_trait<&Self> for Dual<T> {
+            #[inline]
+            fn $assign_func(&mut self, rhs: &Self) {
+                let this = std::mem::replace(self, Self::zero());
+                *self = $trait::$func(this, rhs);
+            }
+        }
+        impl<T: Scalar> $assign_trait for Dual<T> {
+            #[inline]
+            fn $assign_func(&mut self, rhs: Self) {
+                self.$assign_func(&rhs);
+            }
+        }
+    };
+}
+
+define_binary_operations!(Add, add, AddAssign, add_assign);
+define_binary_operations!(Sub, sub, SubAssign, sub_assign);
+define_binary_operations!(Mul, mul, MulAssign, mul_assign);
+define_binary_operations!(Div, div, DivAssign, div_assign);
+
+impl<T: Scalar + Sqrt> Sqrt for Dual<T> {
+    #[inline]
+    fn sqrt(self) -> Self {
+        let val = self.val.sqrt();
+        let tan = self.tan * &T::from(0.5) / &val;
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Exp> Exp for Dual<T> {
+    #[inline]
+    fn exp(self) -> Self {
+        let val = self.val.exp();
+        let tan = self.tan * &val;
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Log> Log for Dual<T> {
+    #[inline]
+    fn log(self) -> Self {
+        let tan = self.tan / &self.val;
+        Self::new(self.val.log(), tan)
+    }
+}
+
+impl<T: Scalar + Powi> Powi for Dual<T> {
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        let tan = match n {
+            0 => T::zero(),
+            _ => self.tan * &T::from(n as f64) * &self.val.clone().powi(n - 1),
+        };
+        Self::new(self.val.powi(n), tan)
+    }
+}
+
+impl<T: Scalar + Powf + Log> Powf for Dual<T> {
+    #[inline]
+    fn powf(self, n: Self) -> Self {
+        let val = self.val.clone().powf(n.val.clone());
+        let mut tan = T::zero();
+        if !self.tan.is_zero() && !n.val.is_zero() {
+            let g = n.val.clone() * &self.val.clone().powf(n.val - &T::one());
+            tan += &(self.tan * &g);
+        }
+        // derivative by the exponent is zero at zero base as that of `Expr`
+        if !n.tan.is_zero() && !self.val.is_zero() {
+            tan += &(n.tan * &val * &self.val.log());
+        }
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Sin + Cos> Sin for Dual<T> {
+    #[inline]
+    fn sin(self) -> Self {
+        let tan = self.tan * &self.val.clone().cos();
+        Self::new(self.val.sin(), tan)
+    }
+}
+
+impl<T: Scalar + Sin + Cos> Cos for Dual<T> {
+    #[inline]
+    fn cos(self) -> Self {
+        let tan = -self.tan * &self.val.clone().sin();
+        Self::new(self.val.cos(), tan)
+    }
+}
+
+impl<T: Scalar + super::Tan> super::Tan for Dual<T> {
+    #[inline]
+    fn tan(self) -> Self {
+        let val = super::Tan::tan(self.val);
+        let tan = self.tan * &(T::one() + val.clone() * &val);
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Asin + Sqrt> Asin for Dual<T> {
+    #[inline]
+    fn asin(self) -> Self {
+        let tan = self.tan / &(T::one() - self.val.clone() * &self.val).sqrt();
+        Self::new(self.val.asin(), tan)
+    }
+}
+
+impl<T: Scalar + Acos + Sqrt> Acos for Dual<T> {
+    #[inline]
+    fn acos(self) -> Self {
+        let tan = -self.tan / &(T::one() - self.val.clone() * &self.val).sqrt();
+        Self::new(self.val.acos(), tan)
+    }
+}
+
+impl<T: Scalar + Atan> Atan for Dual<T> {
+    #[inline]
+    fn atan(self) -> Self {
+        let tan = self.tan / &(T::one() + self.val.clone() * &self.val);
+        Self::new(self.val.atan(), tan)
+    }
+}
+
+impl<T: Scalar + Atan2> Atan2 for Dual<T> {
+    #[inline]
+    fn atan2(self, x: Self) -> Self {
+        let r2 = x.val.clone() * &x.val + &(self.val.clone() * &self.val);
+        let tan = match r2.is_zero() {
+            true => T::zero(),
+            false => (self.tan * &x.val - &(x.tan * &self.val)) / &r2,
+        };
+        Self::new(self.val.atan2(x.val), tan)
+    }
+}
+
+impl<T: Scalar + Tanh> Tanh for Dual<T> {
+    #[inline]
+    fn tanh(self) -> Self {
+        let val = self.val.tanh();
+        let tan = self.tan * &(T::one() - val.clone() * &val);
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Sinh + Cosh> Sinh for Dual<T> {
+    #[inline]
+    fn sinh(self) -> Self {
+        let tan = self.tan * &self.val.clone().cosh();
+        Self::new(self.val.sinh(), tan)
+    }
+}
+
+impl<T: Scalar + Sinh + Cosh> Cosh for Dual<T> {
+    #[inline]
+    fn cosh(self) -> Self {
+        let tan = self.tan * &self.val.clone().sinh();
+        Self::new(self.val.cosh(), tan)
+    }
+}
+
+impl<T: Scalar + Asinh + Sqrt> Asinh for Dual<T> {
+    #[inline]
+    fn asinh(self) -> Self {
+        let tan = self.tan / &(self.val.clone() * &self.val + &T::one()).sqrt();
+        Self::new(self.val.asinh(), tan)
+    }
+}
+
+impl<T: Scalar + Ln1p> Ln1p for Dual<T> {
+    #[inline]
+    fn ln_1p(self) -> Self {
+        let tan = self.tan / &(T::one() + &self.val);
+        Self::new(self.val.ln_1p(), tan)
+    }
+}
+
+impl<T: Scalar + ExpM1> ExpM1 for Dual<T> {
+    #[inline]
+    fn exp_m1(self) -> Self {
+        let val = self.val.exp_m1();
+        let tan = self.tan * &(val.clone() + &T::one());
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Log2> Log2 for Dual<T> {
+    #[inline]
+    fn log2(self) -> Self {
+        let tan = self.tan / &(self.val.clone() * &T::from(std::f64::consts::LN_2));
+        Self::new(self.val.log2(), tan)
+    }
+}
+
+impl<T: Scalar + Log10> Log10 for Dual<T> {
+    #[inline]
+    fn log10(self) -> Self {
+        let tan = self.tan / &(self.val.clone() * &T::from(std::f64::consts::LN_10));
+        Self::new(self.val.log10(), tan)
+    }
+}
+
+impl<T: Scalar + Exp2> Exp2 for Dual<T> {
+    #[inline]
+    fn exp2(self) -> Self {
+        let val = self.val.exp2();
+        let tan = self.tan * &val * &T::from(std::f64::consts::LN_2);
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Cbrt> Cbrt for Dual<T> {
+    #[inline]
+    fn cbrt(self) -> Self {
+        let val = self.val.cbrt();
+        let tan = self.tan / &(val.clone() * &val * &T::from(3.0));
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + Erf + Exp> Erf for Dual<T> {
+    #[inline]
+    fn erf(self) -> Self {
+        let d = T::from(std::f64::consts::FRAC_2_SQRT_PI) * (-(self.val.clone() * &self.val)).exp();
+        Self::new(self.val.erf(), self.tan * &d)
+    }
+}
+
+impl<T: Scalar + Erfc + Exp> Erfc for Dual<T> {
+    #[inline]
+    fn erfc(self) -> Self {
+        let d = T::from(std::f64::consts::FRAC_2_SQRT_PI) * (-(self.val.clone() * &self.val)).exp();
+        Self::new(self.val.erfc(), -(self.tan * &d))
+    }
+}
+
+impl<T: Scalar + NormPdf> NormPdf for Dual<T> {
+    #[inline]
+    fn norm_pdf(self) -> Self {
+        let val = self.val.clone().norm_pdf();
+        let tan = -(self.tan * &self.val * &val);
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + NormCdf + NormPdf> NormCdf for Dual<T> {
+    #[inline]
+    fn norm_cdf(self) -> Self {
+        let tan = self.tan * &self.val.clone().norm_pdf();
+        Self::new(self.val.norm_cdf(), tan)
+    }
+}
+
+impl<T: Scalar + NormInvCdf + NormPdf> NormInvCdf for Dual<T> {
+    #[inline]
+    fn norm_inv_cdf(self) -> Self {
+        let val = self.val.norm_inv_cdf();
+        let tan = self.tan / &val.clone().norm_pdf();
+        Self::new(val, tan)
+    }
+}
+
+impl<T: Scalar + PartialOrd> Abs for Dual<T> {
+    #[inline]
+    fn abs_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self.val, &T::zero())?;
+        Ok(match self.val.partial_cmp(&T::zero()) {
+            Some(std::cmp::Ordering::Less) => -self,
+            Some(std::cmp::Ordering::Greater) => self,
+            None => Self::new(T::from(f64::NAN), T::from(f64::NAN)),
+            _ => {
+                let d = policy.pick(-T::one(), T::one());
+                Self::new(self.val, self.tan * &d)
+            }
+        })
+    }
+}
+
+impl<T: Scalar + PartialOrd> Relu for Dual<T> {
+    #[inline]
+    fn relu_with(self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self.val, &T::zero())?;
+        Ok(match self.val.partial_cmp(&T::zero()) {
+            Some(std::cmp::Ordering::Less) => Self::zero(),
+            Some(std::cmp::Ordering::Greater) => self,
+            None => Self::new(T::from(f64::NAN), T::from(f64::NAN)),
+            _ => {
+                let d = policy.pick(T::zero(), T::one());
+                Self::new(self.val, self.tan * &d)
+            }
+        })
+    }
+}
+
+impl<T: Scalar + PartialOrd> Max for Dual<T> {
+    #[inline]
+    fn max_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self.val, &rhs.val)?;
+        Ok(match self.val.partial_cmp(&rhs.val) {
+            Some(std::cmp::Ordering::Greater) => self,
+            Some(std::cmp::Ordering::Less) => rhs,
+            None => Self::new(T::from(f64::NAN), T::from(f64::NAN)),
+            _ => {
+                let gl = policy.pick(T::zero(), T::one());
+                let gr = policy.pick(T::one(), T::zero());
+                Self::new(self.val, self.tan * &gl + &(rhs.tan * &gr))
+            }
+        })
+    }
+}
+
+impl<T: Scalar + PartialOrd> Min for Dual<T> {
+    #[inline]
+    fn min_with(self, rhs: Self, policy: Subgradient) -> Result<Self, KinkError> {
+        policy.check(&self.val, &rhs.val)?;
+        Ok(match self.val.partial_cmp(&rhs.val) {
+            Some(std::cmp::Ordering::Less) => self,
+            Some(std::cmp::Ordering::Greater) => rhs,
+            None => Self::new(T::from(f64::NAN), T::from(f64::NAN)),
+            _ => {
+                let gl = policy.pick(T::one(), T::zero());
+                let gr = policy.pick(T::zero(), T::one());
+                Self::new(self.val, self.tan * &gl + &(rhs.tan * &gr))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expr, VarGroup};
+
+    use super::*;
+
+    fn price<S: Scalar + Sqrt + Exp + Log + Powi + Powf>(x: &[S]) -> S {
+        let fwd = x[0].clone() * &(x[1].clone() * &x[2]).exp();
+        let vol = (x[3].clone() * &x[3] * &x[2]).sqrt();
+        let df = (x[1].clone() + &S::one()).powf(-x[2].clone()).powi(2);
+        (fwd / &x[4]).log() / &vol - &(vol.clone() * &S::from(0.5)) + &df
+    }
+
+    #[test]
+    fn test_dual_matches_reverse_mode() {
+        let vals = [100.0, 0.02, 1.5, 0.2, 95.0];
+        let dir = [0.3, -1.0, 0.5, 2.0, 0.1];
+
+        let duals: Vec<_> = vals
+            .iter()
+            .zip(dir)
+            .map(|(&v, d)| Dual::new(v, d))
+            .collect();
+        let fwd = price(&duals);
+
+        let grp = VarGroup::new("x");
+        let vars: Vec<_> = vals.iter().map(|&v| grp.val(v)).collect();
+        let exprs: Vec<Expr<f64>> = vars.iter().cloned().map(|v| v.into_expr()).collect();
+        let rev = price(&exprs);
+        let grads = rev.grads();
+
+        assert!((fwd.val() - rev.output()).abs() < 1e-12);
+        let expected: f64 = vars.iter().zip(dir).map(|(v, d)| grads[v.id()] * d).sum();
+        assert!((fwd.tan() - expected).abs() < 1e-12);
+    }
+}
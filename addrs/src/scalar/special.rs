@@ -0,0 +1,249 @@
+//! Special functions for `f64`, which `std` does not provide.
+//!
+//! `erf` and `erfc` are ported from fdlibm and accurate to about one ulp.
+
+// coefficients are kept as published
+#![allow(clippy::excessive_precision)]
+
+const ERX: f64 = 8.45062911510467529297e-01;
+const EFX: f64 = 1.28379167095512586316e-01;
+const PP: [f64; 5] = [
+    1.28379167095512558561e-01,
+    -3.25042107247001499370e-01,
+    -2.84817495755985104766e-02,
+    -5.77027029648944159157e-03,
+    -2.37630166566501626084e-05,
+];
+const QQ: [f64; 5] = [
+    3.97917223959155352819e-01,
+    6.50222499887672944485e-02,
+    5.08130628187576562776e-03,
+    1.32494738004321644526e-04,
+    -3.96022827877536812320e-06,
+];
+const PA: [f64; 7] = [
+    -2.36211856075265944077e-03,
+    4.14856118683748331666e-01,
+    -3.72207876035701323847e-01,
+    3.18346619901161753674e-01,
+    -1.10894694282396677476e-01,
+    3.54783043256182359371e-02,
+    -2.16637559486879084300e-03,
+];
+const QA: [f64; 6] = [
+    1.06420880400844228286e-01,
+    5.40397917702171048937e-01,
+    7.18286544141962662868e-02,
+    1.26171219808761642112e-01,
+    1.36370839120290507362e-02,
+    1.19844998467991074170e-02,
+];
+const RA: [f64; 8] = [
+    -9.86494403484714822705e-03,
+    -6.93858572707181764372e-01,
+    -1.05586262253232909814e+01,
+    -6.23753324503260060396e+01,
+    -1.62396669462573470355e+02,
+    -1.84605092906711035994e+02,
+    -8.12874355063065934246e+01,
+    -9.81432934416914548592e+00,
+];
+const SA: [f64; 8] = [
+    1.96512716674392571292e+01,
+    1.37657754143519042600e+02,
+    4.34565877475229228821e+02,
+    6.45387271733267880336e+02,
+    4.29008140027567833386e+02,
+    1.08635005541779435134e+02,
+    6.57024977031928170135e+00,
+    -6.04244152148580987438e-02,
+];
+const RB: [f64; 7] = [
+    -9.86494292470009928597e-03,
+    -7.99283237680523006574e-01,
+    -1.77579549177547519889e+01,
+    -1.60636384855821916062e+02,
+    -6.37566443368389627722e+02,
+    -1.02509513161107724954e+03,
+    -4.83519191608651397019e+02,
+];
+const SB: [f64; 7] = [
+    3.03380607434824582924e+01,
+    3.25792512996573918826e+02,
+    1.53672958608443695994e+03,
+    3.19985821950859553908e+03,
+    2.55305040643316442583e+03,
+    4.74528541206955367215e+02,
+    -2.24409524465858183362e+01,
+];
+
+/// Evaluates `c[0] + z * (c[1] + z * (...))`.
+#[inline]
+fn _poly(c: &[f64], z: f64) -> f64 {
+    c.iter().rev().fold(0.0, |acc, c| acc * z + c)
+}
+
+/// `1 + z * (c[0] + z * (...))`.
+#[inline]
+fn _poly1(c: &[f64], z: f64) -> f64 {
+    1.0 + z * _poly(c, z)
+}
+
+/// `erf(x) - x` for `|x| < 0.84375` divided by `x`.
+#[inline]
+fn _small(x: f64) -> f64 {
+    let z = x * x;
+    _poly(&PP, z) / _poly1(&QQ, z)
+}
+
+/// `erf(|x|) - erx` for `0.84375 <= |x| < 1.25`.
+#[inline]
+fn _middle(ax: f64) -> f64 {
+    let s = ax - 1.0;
+    _poly(&PA, s) / _poly1(&QA, s)
+}
+
+/// `erfc(|x|)` for `1.25 <= |x| < 28`.
+#[inline]
+fn _tail(ax: f64) -> f64 {
+    let s = 1.0 / (ax * ax);
+    let (r, s) = if ax < 1.0 / 0.35 {
+        (_poly(&RA, s), _poly1(&SA, s))
+    } else {
+        (_poly(&RB, s), _poly1(&SB, s))
+    };
+    // split to reduce the error of `exp(-x * x)`
+    let z = f64::from_bits(ax.to_bits() & 0xffff_ffff_0000_0000);
+    (-z * z - 0.5625).exp() * ((z - ax) * (z + ax) + r / s).exp() / ax
+}
+
+pub(super) fn erf(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    let ax = x.abs();
+    if ax < 0.84375 {
+        if ax < 3.725290298461914e-9 {
+            return x + EFX * x;
+        }
+        return x + x * _small(x);
+    }
+    let y = if ax < 1.25 {
+        ERX + _middle(ax)
+    } else if ax < 6.0 {
+        1.0 - _tail(ax)
+    } else {
+        1.0
+    };
+    y.copysign(x)
+}
+
+pub(super) fn erfc(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    let ax = x.abs();
+    if ax < 0.84375 {
+        if ax < 1.3877787807814457e-17 {
+            return 1.0 - x;
+        }
+        let y = x * _small(x);
+        return if x < 0.25 {
+            1.0 - (x + y)
+        } else {
+            0.5 - (y + (x - 0.5))
+        };
+    }
+    if ax < 1.25 {
+        let p = _middle(ax);
+        return if x >= 0.0 {
+            1.0 - ERX - p
+        } else {
+            1.0 + (ERX + p)
+        };
+    }
+    if ax < 28.0 {
+        let r = _tail(ax);
+        return if x > 0.0 { r } else { 2.0 - r };
+    }
+    if x > 0.0 {
+        0.0
+    } else {
+        2.0
+    }
+}
+
+const FRAC_1_SQRT_2PI: f64 = 0.398_942_280_401_432_7;
+
+pub(super) fn norm_pdf(x: f64) -> f64 {
+    FRAC_1_SQRT_2PI * (-0.5 * x * x).exp()
+}
+
+pub(super) fn norm_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x * std::f64::consts::FRAC_1_SQRT_2)
+}
+
+const A: [f64; 6] = [
+    -3.969683028665376e+01,
+    2.209460984245205e+02,
+    -2.759285104469687e+02,
+    1.383577518672690e+02,
+    -3.066479806614716e+01,
+    2.506628277459239e+00,
+];
+const B: [f64; 5] = [
+    -5.447609879822406e+01,
+    1.615858368580409e+02,
+    -1.556989798598866e+02,
+    6.680131188771972e+01,
+    -1.328068155288572e+01,
+];
+const C: [f64; 6] = [
+    -7.784894002430293e-03,
+    -3.223964580411365e-01,
+    -2.400758277161838e+00,
+    -2.549732539343734e+00,
+    4.374664141464968e+00,
+    2.938163982698783e+00,
+];
+const D: [f64; 4] = [
+    7.784695709041462e-03,
+    3.224671290700398e-01,
+    2.445134137142996e+00,
+    3.754408661907416e+00,
+];
+
+/// Acklam's approximation refined by one step of Halley's method.
+pub(super) fn norm_inv_cdf(p: f64) -> f64 {
+    if p.is_nan() || !(0.0..=1.0).contains(&p) {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    let horner = |c: &[f64], x: f64| c.iter().fold(0.0, |acc, c| acc * x + c);
+    let tail = |q: f64| {
+        let q = (-2.0 * q.ln()).sqrt();
+        horner(&C, q) / (horner(&D, q) * q + 1.0)
+    };
+    let x = if p < 0.02425 {
+        tail(p)
+    } else if p <= 1.0 - 0.02425 {
+        let q = p - 0.5;
+        let r = q * q;
+        horner(&A, r) * q / (horner(&B, r) * r + 1.0)
+    } else {
+        -tail(1.0 - p)
+    };
+    // refinement, where the error is measured on the side with the smaller probability
+    let e = if x < 0.0 {
+        norm_cdf(x) - p
+    } else {
+        (1.0 - p) - norm_cdf(-x)
+    };
+    let u = e / norm_pdf(x);
+    x - u / (1.0 + 0.5 * x * u)
+}
@@ -0,0 +1,138 @@
+use crate::{graph::CmpKind, private::_Select, Expr};
+
+/// Condition of [`Expr::select`].
+///
+/// Comparisons of expressions are recorded in the graph and decided again
+/// whenever it is evaluated again, e.g. by [`crate::Replay`] or [`crate::Program`].
+/// Constant conditions are fixed when the expression is built.
+#[derive(Debug, Clone)]
+pub enum Cond<T> {
+    Const(bool),
+    Cmp(CmpKind, Expr<T>, Expr<T>),
+}
+
+impl<T> Cond<T> {
+    #[inline]
+    pub fn lt(x: impl Into<Expr<T>>, y: impl Into<Expr<T>>) -> Self {
+        Self::Cmp(CmpKind::Lt, x.into(), y.into())
+    }
+    #[inline]
+    pub fn le(x: impl Into<Expr<T>>, y: impl Into<Expr<T>>) -> Self {
+        Self::Cmp(CmpKind::Le, x.into(), y.into())
+    }
+    #[inline]
+    pub fn gt(x: impl Into<Expr<T>>, y: impl Into<Expr<T>>) -> Self {
+        Self::Cmp(CmpKind::Gt, x.into(), y.into())
+    }
+    #[inline]
+    pub fn ge(x: impl Into<Expr<T>>, y: impl Into<Expr<T>>) -> Self {
+        Self::Cmp(CmpKind::Ge, x.into(), y.into())
+    }
+}
+
+impl<T: PartialOrd> Cond<T> {
+    /// Decides the condition with current outputs.
+    #[inline]
+    pub fn eval(&self) -> bool {
+        match self {
+            Self::Const(c) => *c,
+            Self::Cmp(cmp, x, y) => cmp.eval(x.output(), y.output()),
+        }
+    }
+}
+
+impl<T> From<bool> for Cond<T> {
+    #[inline]
+    fn from(c: bool) -> Self {
+        Self::Const(c)
+    }
+}
+
+impl<T: Clone + PartialOrd> Expr<T> {
+    /// Piecewise expression which is `a` if `cond` holds and `b` otherwise.
+    ///
+    /// Derivatives flow only into the branch taken,
+    /// so the other branch may be undefined, e.g. `log` of a negative value.
+    pub fn select(cond: impl Into<Cond<T>>, a: Self, b: Self) -> Self {
+        let cond = cond.into();
+        let c = cond.eval();
+        match cond {
+            Cond::Const(_) => match c {
+                true => a,
+                false => b,
+            },
+            Cond::Cmp(cmp, x, y) => _Select::create(cmp, x, y, a, b, c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        graph::Node,
+        scalar::{Log, Sqrt},
+        Graph, Program, Replay, Tape, VarGroup,
+    };
+
+    use super::*;
+
+    /// `sqrt(x)` for positive `x` and `-log(-x)` otherwise.
+    fn piecewise(x: &Expr<f64>) -> Expr<f64> {
+        let pos = x.clone().sqrt();
+        let neg = -(-x.clone()).log();
+        Expr::select(Cond::gt(x.clone(), 0.0), pos, neg)
+    }
+
+    #[test]
+    fn test_select() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(4.0).into_expr();
+        let e = piecewise(&x);
+        assert_eq!(*e.output(), 2.0);
+        // the other branch is NaN
+        assert_eq!(e.grads()[&x], 0.25);
+
+        let graph = Graph::from(&e);
+        assert!(matches!(
+            graph.nodes()[graph.outputs()[0]],
+            Node::Select { cond: true, .. }
+        ));
+        assert_eq!(e.to_string(), "select(x#0 > 0, sqrt(x#0), -log(-x#0))");
+
+        // the branch is decided again at new values
+        let id = x.as_var().unwrap().id();
+        let mut replay = Replay::new(std::slice::from_ref(&e));
+        assert!(replay.set(id, -2.0));
+        replay.forward();
+        assert_eq!(*replay.output(0), -2.0_f64.ln());
+        assert_eq!(replay.grads(0)[&x], 0.5);
+
+        let mut program = e.compile();
+        let mut grad = [0.0];
+        assert_eq!(program.eval_grad(&[-2.0], &mut grad), -2.0_f64.ln());
+        assert_eq!(grad, [0.5]);
+        assert_eq!(program.eval_grad(&[9.0], &mut grad), 3.0);
+        assert_eq!(grad, [1.0 / 6.0]);
+
+        // restored and taped expressions agree
+        let restored = graph.to_exprs().unwrap().pop().unwrap();
+        assert_eq!(restored.grads()[&x], 0.25);
+        let tape = Tape::new();
+        let t = tape.var(x.as_var().unwrap().clone());
+        let e = piecewise(&t);
+        assert_eq!((*e.output(), e.grads()[&x]), (2.0, 0.25));
+    }
+
+    #[test]
+    fn test_select_const() {
+        let grp = VarGroup::new("x");
+        let x = grp.val(4.0).into_expr();
+        let y = grp.val(1.0).into_expr();
+        // constant conditions do not create nodes
+        let e = Expr::select(false, x.clone(), y.clone() * 2.0);
+        assert_eq!(Graph::from(&e).nodes().len(), 3);
+        let e = Expr::select(Cond::le(1.0, 2.0), x.clone(), y.clone());
+        assert_eq!(e.grads()[&x], 1.0);
+        assert_eq!(Program::new(&e).len(), 1);
+    }
+}
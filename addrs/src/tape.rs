@@ -0,0 +1,110 @@
+use crate::{
+    private::{_Tape, _TapeRef},
+    Expr, Var,
+};
+
+/// Recording mode backed by a flat list of operations.
+///
+/// Operations on expressions created by [`Tape::var`] are appended to this tape
+/// instead of allocating a node for each of them.
+/// Inputs are referred by indices, so the backward pass is one linear reverse scan
+/// and dropping the tape does not walk the graph.
+///
+/// Expressions which are not recorded on the tape can still be mixed.
+/// They are registered as inputs of the tape at their first use.
+#[derive(Debug, Clone)]
+pub struct Tape<T>(_TapeRef<T>);
+
+impl<T> Tape<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self(_Tape::new())
+    }
+    /// Number of recorded entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Clone> Tape<T> {
+    /// Registers a variable to this tape.
+    #[inline]
+    pub fn var(&self, var: Var<T>) -> Expr<T> {
+        let (id, val) = var.into_parts();
+        _Tape::var(&self.0, id, val).into()
+    }
+}
+
+impl<T> Default for Tape<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scalar::{Exp, Log, Sqrt},
+        Expr, VarGroup,
+    };
+
+    use super::*;
+
+    fn f(x: &Expr<f64>, y: &Expr<f64>, z: &Expr<f64>) -> Expr<f64> {
+        let df = (-x.clone() * y).exp();
+        (df.clone() * z + z.clone().sqrt() / &df - y.clone().log()) * 2.0 - 1.0
+    }
+
+    #[test]
+    fn test_tape_grads() {
+        let grp = VarGroup::new("x");
+        let vars = [grp.val(0.3), grp.val(1.2), grp.val(2.5)];
+        let [x, y, z] = vars.clone().map(Var::into_expr);
+        let expected = f(&x, &y, &z);
+
+        let tape = Tape::new();
+        let [x, y, z] = vars.map(|v| tape.var(v));
+        // mixing with an expression outside of the tape
+        let z = z.clone() * 0.5 + grp.val(0.5).into_expr() * &z;
+        let actual = f(&x, &y, &z);
+
+        assert!(!tape.is_empty());
+        assert!((actual.output() - expected.output()).abs() < 1e-12);
+        let expected = expected.grads();
+        let actual = actual.grads();
+        assert_eq!(actual.len(), expected.len() + 1);
+        for (id, g) in expected {
+            assert!((actual[&id] - g).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_tape_long_chain() {
+        let grp = VarGroup::new("x");
+        let tape = Tape::new();
+        let x = grp.val(1.1_f64);
+        let id = x.id().clone();
+        let x = tape.var(x);
+        let mut res = Expr::constant(0.0);
+        for _ in 0..100000 {
+            res += x.clone() * &x;
+        }
+        let grads = res.grads();
+        assert!((grads[&id] - 220000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tape_mixing_different_tapes() {
+        let grp = VarGroup::new("x");
+        let x = Tape::new().var(grp.val(1.0));
+        let y = Tape::new().var(grp.val(2.0));
+        let _ = x + y;
+    }
+}
@@ -1,8 +1,12 @@
-use std::{borrow::Cow, rc::Rc, sync::Mutex};
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use crate::Expr;
+use crate::{private::_Rc, Expr};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Id {
     pub group: usize,
     pub name: Cow<'static, str>,
@@ -28,21 +32,29 @@ impl<T> Var<T> {
     pub fn into_expr(self) -> Expr<T> {
         self.into()
     }
+    #[inline]
+    pub(crate) fn from_parts(id: Id, val: T) -> Self {
+        Self { val, id }
+    }
+    #[inline]
+    pub(crate) fn into_parts(self) -> (Id, T) {
+        (self.id, self.val)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct VarGroup {
     id: usize,
     name: Cow<'static, str>,
-    cnt: Rc<Mutex<usize>>,
+    cnt: _Rc<AtomicUsize>,
 }
 
 impl VarGroup {
     pub fn new(name: impl Into<String>) -> Self {
-        static ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
-        let id = ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        static ID: AtomicUsize = AtomicUsize::new(0);
+        let id = ID.fetch_add(1, Ordering::SeqCst);
         let name = name.into().into();
-        let cnt = Rc::new(Mutex::new(0));
+        let cnt = _Rc::new(AtomicUsize::new(0));
         Self { id, name, cnt }
     }
 
@@ -55,14 +67,27 @@ impl VarGroup {
         self.name.clone()
     }
 
+    /// Number of variables created in this group.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cnt.load(Ordering::SeqCst)
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Makes variables created later numbered at least `len`.
+    #[inline]
+    pub(crate) fn reserve(&self, len: usize) {
+        self.cnt.fetch_max(len, Ordering::SeqCst);
+    }
+
     pub fn val<T>(&self, val: T) -> Var<T> {
-        let mut cnt = self.cnt.lock().unwrap();
         let id = Id {
             group: self.id,
             name: self.name.clone(),
-            num: *cnt,
+            num: self.cnt.fetch_add(1, Ordering::SeqCst),
         };
-        *cnt += 1;
         Var { val, id }
     }
 }